@@ -0,0 +1,28 @@
+//! Runs the engine's `bench` command and prints its summary. The node count
+//! over the fixed positions is what search changes are compared by.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn main() {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_weakfish-rs"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to start the engine");
+
+    engine
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"bench\nquit\n")
+        .expect("Failed to send bench");
+
+    let output = engine.wait_with_output().expect("Engine crashed");
+
+    // Skip the banner and the search info
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.starts_with("info") && !line.starts_with("Weakfish"))
+        .for_each(|line| println!("{}", line));
+}
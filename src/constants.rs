@@ -90,12 +90,12 @@ pub const KINGS: [i8; 64] = [
 ];
 
 pub fn value(piece: Piece) -> f32 {
-    return match piece {
+    match piece {
         Piece::Pawn => { PAWN },
         Piece::Knight => { KNIGHT },
         Piece::Bishop => { BISHOP },
         Piece::Rook => { ROOK },
         Piece::Queen => { QUEEN },
         Piece::King => { CHECKMATE },
-    };
+    }
 }
\ No newline at end of file
@@ -1,52 +1,94 @@
-use chess::{Board, Color, ChessMove};
 
-use std::str::FromStr;
-use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
 use crate::uci;
+use crate::options::{self, OptionValue};
 use crate::uci::{Status, Memory};
-use crate::search::transposition::{TT, TableEntry};
-use crate::constants::CHECKMATE;
-use crate::search::negamax;
+use crate::search::transposition::{TT, HashTable, DEFAULT_HASH_MB, DEFAULT_SEED};
+use crate::search::iterative::iterative_deepening;
+use crate::search::bench::bench;
+use crate::search::limits::Limits;
+use crate::search::searcher::Searcher;
+use crate::search::score::Score;
+use crate::search::extensions::ExtensionPolicy;
+use crate::search::pruning::PruningMargins;
+use crate::search::strength::{Strength, MAX_LEVEL, MAX_ELO};
+
+// How often a finished infinite search checks for stop
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(1);
 
 pub struct Weakfish {
-    pub is_interruption: bool,
+    pub stop: Arc<AtomicBool>,
+    pub move_overhead: Duration,
+    pub seed: u64,
+    pub level: u8,
+    pub limit_strength: bool,
+    pub elo: u16,
+    // Centipawns
+    pub contempt: i32,
+    pub extensions: ExtensionPolicy,
+    pub margins: PruningMargins,
+    pub tt: TT,
+    pub hash_table: Arc<Mutex<HashTable>>,
+    search_thread: Option<JoinHandle<()>>,
 }
 
 impl Weakfish {
     pub fn new() -> Weakfish {
         Weakfish {
-            is_interruption: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            move_overhead: Duration::from_millis(uci::DEFAULT_MOVE_OVERHEAD),
+            seed: DEFAULT_SEED,
+            level: MAX_LEVEL,
+            limit_strength: false,
+            elo: MAX_ELO,
+            contempt: 0,
+            extensions: ExtensionPolicy::default(),
+            margins: PruningMargins::default(),
+            tt: TT::from_seed(DEFAULT_SEED),
+            hash_table: Arc::new(Mutex::new(HashTable::new(DEFAULT_HASH_MB))),
+            search_thread: None,
         }
     }
 
     pub fn run(&mut self) {
         let mut mem = Memory::new();
-        
-        // Creates the transposition table
-        let mut tt = TT::new();
-        let mut hash_table: HashMap<u64, TableEntry> = HashMap::new();
-        tt.update(&Board::default());
 
         println!("Weakfish v0.1.0 by juliohq 2022");
 
+        // Commands keep coming in while the search thread is busy
+        let input = uci::input_channel();
+
         loop {
-            let input = uci::get_input();
-            
+            // Stdin was closed
+            let Ok(input) = input.recv() else {
+                self.quit();
+                break;
+            };
+
             match uci::parse(input, &mut mem) {
                 Status::Continue => {},
-                Status::Go(depth) => {
-                    let move_str = self.go(mem.pos.clone(), 7, &tt, &mut hash_table);
-                    uci::best_move(move_str);
+                Status::Go(limits) => {
+                    self.go(&mem, limits);
+                },
+                Status::Stop => {
+                    self.stop();
                 },
-                Status::Position(fen, moves) => {
-                    let mut board = Board::from_str(fen.as_str()).unwrap();
-                    
-                    for m in moves {
-                        board = board.make_move_new(ChessMove::from_str(m.as_str()).unwrap());
-                    }
-                    
-                    mem.pos = board.to_string();
+                Status::Bench(depth) => {
+                    self.stop();
+
+                    let (nodes, elapsed) = bench(depth);
+                    uci::bench_result(nodes, elapsed);
+                },
+                Status::NewGame => {
+                    self.new_game(&mut mem);
+                },
+                Status::SetOption(name, value) => {
+                    self.stop();
+                    self.set_option(&name, &value);
                 },
                 Status::Quit => {
                     self.quit();
@@ -56,14 +98,138 @@ impl Weakfish {
         }
     }
 
-    pub fn go(&self, pos: String, depth: u8, table: &TT, hashmap: &mut HashMap<u64, TableEntry>) -> String {
-        let board = Board::from_str(pos.as_str()).unwrap();
-    
-        let best = negamax::negamax(self, &board, depth, -CHECKMATE, CHECKMATE, if board.side_to_move() == Color::White { 1f32 } else { -1f32 }, 0, table, hashmap).0.unwrap();
-        return best.to_string();
+    /// Forgets everything learnt from the previous game so each game plays
+    /// out as if the engine had just been started.
+    pub fn new_game(&mut self, mem: &mut Memory) {
+        self.stop();
+
+        *mem = Memory::new();
+        self.hash_table.lock().unwrap().clear();
+    }
+
+    /// Starts searching `pos` on the search thread, which prints `bestmove` when done.
+    pub fn go(&mut self, mem: &Memory, mut limits: Limits) {
+        // Only one search at a time
+        self.stop();
+
+        let board = mem.board();
+        let table = self.tt;
+
+        self.stop.store(false, Ordering::SeqCst);
+
+        let strength = if self.limit_strength { Strength::from_elo(self.elo) } else { Strength::from_level(self.level) };
+        strength.limit(&mut limits);
+
+        let mut searcher = Searcher::new(limits, board.side_to_move(), self.move_overhead, self.seed, Arc::clone(&self.stop));
+        searcher.strength = strength;
+        searcher.contempt = Score::cp(self.contempt);
+        searcher.extensions = self.extensions;
+        searcher.margins = self.margins;
+        searcher.history = mem.history.iter().map(|(b, halfmove)| (table.hash(b), *halfmove)).collect();
+
+        let hash_table = Arc::clone(&self.hash_table);
+
+        self.search_thread = Some(thread::spawn(move || {
+            let pv = iterative_deepening(&mut searcher, &board, &table, &mut hash_table.lock().unwrap());
+
+            // An infinite search only answers once the GUI sends stop
+            while searcher.limits.infinite && !searcher.is_stopped() {
+                thread::sleep(STOP_POLL_INTERVAL);
+            }
+
+            // No legal moves, but the GUI still expects an answer
+            let best = pv.first().map_or("0000".to_string(), |m| m.to_string());
+            uci::best_move(best, pv.get(1).copied());
+        }));
+    }
+
+    /// Interrupts the running search, if any, and waits for its `bestmove`.
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.wait();
+    }
+
+    /// Blocks until the running search, if any, is over. Only safe once the
+    /// search was told to stop, since an infinite one never ends by itself.
+    fn wait(&mut self) {
+        if let Some(handle) = self.search_thread.take() {
+            handle.join().expect("Search thread panicked");
+        }
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) {
+        let Some(option) = options::find(name) else {
+            uci::unknown_option(name);
+            return;
+        };
+
+        let value = match option.parse(value) {
+            Ok(v) => v,
+            Err(e) => {
+                uci::invalid_option(option.name, &e);
+                return;
+            }
+        };
+
+        match (option.name, value) {
+            ("Hash", OptionValue::Spin(mb)) => {
+                self.hash_table.lock().unwrap().resize(mb as usize);
+            },
+            ("Clear Hash", _) => {
+                self.hash_table.lock().unwrap().clear();
+            },
+            ("Level", OptionValue::Spin(level)) => {
+                self.level = level as u8;
+            },
+            ("UCI_LimitStrength", OptionValue::Check(limit_strength)) => {
+                self.limit_strength = limit_strength;
+            },
+            ("UCI_Elo", OptionValue::Spin(elo)) => {
+                self.elo = elo as u16;
+            },
+            ("Contempt", OptionValue::Spin(contempt)) => {
+                self.contempt = contempt as i32;
+            },
+            ("Move Overhead", OptionValue::Spin(ms)) => {
+                self.move_overhead = Duration::from_millis(ms as u64);
+            },
+            ("Check Extension", OptionValue::Check(enabled)) => {
+                self.extensions.check = enabled;
+            },
+            ("Single Reply Extension", OptionValue::Check(enabled)) => {
+                self.extensions.single_reply = enabled;
+            },
+            ("Passed Pawn Extension", OptionValue::Check(enabled)) => {
+                self.extensions.passed_pawn = enabled;
+            },
+            ("Singular Extension", OptionValue::Check(enabled)) => {
+                self.extensions.singular = enabled;
+            },
+            ("Max Extensions", OptionValue::Spin(plies)) => {
+                self.extensions.max_per_path = plies as u8;
+            },
+            ("Reverse Futility Margin", OptionValue::Spin(margin)) => {
+                self.margins.reverse_futility = margin as i32;
+            },
+            ("Futility Margin", OptionValue::Spin(margin)) => {
+                self.margins.futility = margin as i32;
+            },
+            ("Razoring Margin", OptionValue::Spin(margin)) => {
+                self.margins.razoring = margin as i32;
+            },
+            ("Seed", OptionValue::Spin(seed)) => {
+                self.seed = seed as u64;
+                self.tt = TT::from_seed(self.seed);
+
+                // Stored hashes are meaningless with the new keys
+                self.hash_table.lock().unwrap().clear();
+            },
+            // Threads and MultiPV only take their single value
+            _ => {}
+        }
     }
 
     pub fn quit(&mut self) {
-        self.is_interruption = true;
+        self.stop();
     }
-}
\ No newline at end of file
+}
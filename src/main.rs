@@ -2,6 +2,7 @@ mod engine;
 mod uci;
 mod search;
 mod constants;
+mod options;
 
 fn main() {
     let mut weakfish = engine::Weakfish::new();
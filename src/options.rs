@@ -0,0 +1,179 @@
+use std::fmt;
+
+use crate::uci::{DEFAULT_MOVE_OVERHEAD, MAX_MOVE_OVERHEAD, MAX_CONTEMPT};
+use crate::search::transposition::{DEFAULT_HASH_MB, MAX_HASH_MB, DEFAULT_SEED};
+use crate::search::strength::{MIN_LEVEL, MAX_LEVEL, MIN_ELO, MAX_ELO};
+use crate::search::extensions::{DEFAULT_MAX_EXTENSIONS, MAX_EXTENSIONS};
+use crate::search::pruning::{
+    DEFAULT_REVERSE_FUTILITY_MARGIN,
+    DEFAULT_FUTILITY_MARGIN,
+    DEFAULT_RAZORING_MARGIN,
+    MAX_PRUNING_MARGIN,
+};
+
+// Not every option type is in use yet
+#[allow(dead_code)]
+pub enum OptionType {
+    Spin { default: i64, min: i64, max: i64 },
+    Check { default: bool },
+    Combo { default: &'static str, vars: &'static [&'static str] },
+    Button,
+    String { default: &'static str },
+}
+
+/// A value accepted by `setoption`, already validated against its option.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionValue {
+    Spin(i64),
+    Check(bool),
+    Combo(String),
+    Button,
+    String(String),
+}
+
+pub struct EngineOption {
+    pub name: &'static str,
+    pub kind: OptionType,
+}
+
+/// Every option the engine advertises, in the order they are listed.
+pub const OPTIONS: &[EngineOption] = &[
+    EngineOption {
+        name: "Hash",
+        kind: OptionType::Spin { default: DEFAULT_HASH_MB as i64, min: 1, max: MAX_HASH_MB as i64 },
+    },
+    EngineOption {
+        name: "Clear Hash",
+        kind: OptionType::Button,
+    },
+    // The search is single-threaded
+    EngineOption {
+        name: "Threads",
+        kind: OptionType::Spin { default: 1, min: 1, max: 1 },
+    },
+    // Full strength unless asked otherwise
+    EngineOption {
+        name: "Level",
+        kind: OptionType::Spin { default: MAX_LEVEL as i64, min: MIN_LEVEL as i64, max: MAX_LEVEL as i64 },
+    },
+    EngineOption {
+        name: "UCI_LimitStrength",
+        kind: OptionType::Check { default: false },
+    },
+    EngineOption {
+        name: "UCI_Elo",
+        kind: OptionType::Spin { default: MAX_ELO as i64, min: MIN_ELO as i64, max: MAX_ELO as i64 },
+    },
+    // Only the main line is searched
+    EngineOption {
+        name: "MultiPV",
+        kind: OptionType::Spin { default: 1, min: 1, max: 1 },
+    },
+    EngineOption {
+        name: "Contempt",
+        kind: OptionType::Spin { default: 0, min: -(MAX_CONTEMPT as i64), max: MAX_CONTEMPT as i64 },
+    },
+    EngineOption {
+        name: "Move Overhead",
+        kind: OptionType::Spin { default: DEFAULT_MOVE_OVERHEAD as i64, min: 0, max: MAX_MOVE_OVERHEAD as i64 },
+    },
+    // Search tuning: which moves are searched a ply deeper...
+    EngineOption {
+        name: "Check Extension",
+        kind: OptionType::Check { default: true },
+    },
+    EngineOption {
+        name: "Single Reply Extension",
+        kind: OptionType::Check { default: true },
+    },
+    EngineOption {
+        name: "Passed Pawn Extension",
+        kind: OptionType::Check { default: true },
+    },
+    EngineOption {
+        name: "Singular Extension",
+        kind: OptionType::Check { default: true },
+    },
+    // ...and how many extra plies a single line may get
+    EngineOption {
+        name: "Max Extensions",
+        kind: OptionType::Spin { default: DEFAULT_MAX_EXTENSIONS as i64, min: 0, max: MAX_EXTENSIONS as i64 },
+    },
+    // Pruning margins, in centipawns per ply
+    EngineOption {
+        name: "Reverse Futility Margin",
+        kind: OptionType::Spin { default: DEFAULT_REVERSE_FUTILITY_MARGIN as i64, min: 0, max: MAX_PRUNING_MARGIN as i64 },
+    },
+    EngineOption {
+        name: "Futility Margin",
+        kind: OptionType::Spin { default: DEFAULT_FUTILITY_MARGIN as i64, min: 0, max: MAX_PRUNING_MARGIN as i64 },
+    },
+    EngineOption {
+        name: "Razoring Margin",
+        kind: OptionType::Spin { default: DEFAULT_RAZORING_MARGIN as i64, min: 0, max: MAX_PRUNING_MARGIN as i64 },
+    },
+    EngineOption {
+        name: "Seed",
+        kind: OptionType::Spin { default: DEFAULT_SEED as i64, min: 0, max: i32::MAX as i64 },
+    },
+];
+
+/// Looks an option up by name, which UCI compares case-insensitively.
+pub fn find(name: &str) -> Option<&'static EngineOption> {
+    OPTIONS.iter().find(|o| o.name.eq_ignore_ascii_case(name))
+}
+
+impl EngineOption {
+    /// Validates the `value` of a `setoption` command for this option.
+    pub fn parse(&self, value: &str) -> Result<OptionValue, String> {
+        match self.kind {
+            OptionType::Spin { min, max, .. } => {
+                let v: i64 = value.parse().map_err(|_| format!("{} is not a number", value))?;
+
+                if v < min || v > max {
+                    return Err(format!("{} is out of range {}..{}", v, min, max));
+                }
+
+                Ok(OptionValue::Spin(v))
+            },
+            OptionType::Check { .. } => {
+                match value {
+                    "true" => Ok(OptionValue::Check(true)),
+                    "false" => Ok(OptionValue::Check(false)),
+                    _ => Err(format!("{} is not true or false", value)),
+                }
+            },
+            OptionType::Combo { vars, .. } => {
+                vars.iter()
+                    .find(|var| var.eq_ignore_ascii_case(value))
+                    .map(|var| OptionValue::Combo(var.to_string()))
+                    .ok_or(format!("{} is not one of {}", value, vars.join(", ")))
+            },
+            OptionType::Button => Ok(OptionValue::Button),
+            OptionType::String { .. } => Ok(OptionValue::String(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for EngineOption {
+    /// The `option name ...` line sent in reply to `uci`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "option name {} type ", self.name)?;
+
+        match self.kind {
+            OptionType::Spin { default, min, max } => write!(f, "spin default {} min {} max {}", default, min, max),
+            OptionType::Check { default } => write!(f, "check default {}", default),
+            OptionType::Combo { default, vars } => {
+                write!(f, "combo default {}", default)?;
+
+                for var in vars {
+                    write!(f, " var {}", var)?;
+                }
+
+                Ok(())
+            },
+            OptionType::Button => write!(f, "button"),
+            OptionType::String { default } => write!(f, "string default {}", default),
+        }
+    }
+}
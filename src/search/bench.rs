@@ -0,0 +1,56 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use chess::Board;
+
+use crate::search::iterative::iterative_deepening;
+use crate::search::limits::Limits;
+use crate::search::searcher::Searcher;
+use crate::search::transposition::{TT, HashTable, DEFAULT_HASH_MB, DEFAULT_SEED};
+
+/// Positions searched by `bench`: the opening, an open game, a crowded
+/// middlegame full of tactics, a rook endgame and a quiet middlegame.
+pub const BENCH_POSITIONS: &[&str] = &[
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+];
+
+pub const BENCH_DEPTH: u8 = 6;
+
+/// Searches every bench position to `depth` from scratch, with the default
+/// settings, and returns the total nodes and time. The node count only
+/// changes when the search does, which makes it the number to compare.
+pub fn bench(depth: u8) -> (u64, Duration) {
+    let table = TT::from_seed(DEFAULT_SEED);
+    let start = Instant::now();
+    let mut nodes = 0;
+
+    for fen in BENCH_POSITIONS {
+        let board = Board::from_str(fen).unwrap();
+        let mut hash_table = HashTable::new(DEFAULT_HASH_MB);
+        let limits = Limits { depth: Some(depth), ..Limits::default() };
+
+        let mut searcher = Searcher::new(limits, board.side_to_move(), Duration::ZERO, DEFAULT_SEED, Arc::new(AtomicBool::new(false)));
+        searcher.history = vec![(table.hash(&board), 0)];
+
+        iterative_deepening(&mut searcher, &board, &table, &mut hash_table);
+        nodes += searcher.nodes;
+    }
+
+    (nodes, start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::bench;
+
+    #[test]
+    fn bench_is_reproducible() {
+        assert_eq!(bench(3).0, bench(3).0);
+    }
+}
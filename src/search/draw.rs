@@ -0,0 +1,129 @@
+use chess::{Board, BoardStatus, Piece, BitBoard, EMPTY};
+
+// Fifty moves without captures or pawn moves, counted in plies
+const FIFTY_MOVE_PLIES: u16 = 100;
+
+const DARK_SQUARES: BitBoard = BitBoard(0xAA55_AA55_AA55_AA55);
+
+/// Whether the game is drawn at the last position of `history`, given as
+/// (hash, halfmove clock) pairs from the start of the game to `board`.
+pub fn is_draw(board: &Board, history: &[(u64, u16)]) -> bool {
+    is_repetition(history) || is_fifty_moves(board, history) || is_insufficient_material(board)
+}
+
+/// Whether the last position already occurred since the last capture or pawn
+/// move. Positions can only repeat with the same side to move, so every
+/// other one is skipped.
+pub fn is_repetition(history: &[(u64, u16)]) -> bool {
+    let Some(&(hash, halfmove)) = history.last() else {
+        return false;
+    };
+
+    history
+        .iter()
+        .rev()
+        .skip(2)
+        .step_by(2)
+        .take(halfmove as usize / 2)
+        .any(|(h, _)| *h == hash)
+}
+
+/// Whether the fifty-move rule applies, unless the last move mated.
+pub fn is_fifty_moves(board: &Board, history: &[(u64, u16)]) -> bool {
+    match history.last() {
+        Some(&(_, halfmove)) => halfmove >= FIFTY_MOVE_PLIES && board.status() != BoardStatus::Checkmate,
+        None => false,
+    }
+}
+
+/// Whether neither side has enough material left to mate: bare kings, a
+/// single minor piece, or bishops that all stand on the same colour.
+pub fn is_insufficient_material(board: &Board) -> bool {
+    let heavy = *board.pieces(Piece::Pawn) | *board.pieces(Piece::Rook) | *board.pieces(Piece::Queen);
+
+    if heavy != EMPTY {
+        return false;
+    }
+
+    let knights = *board.pieces(Piece::Knight);
+    let bishops = *board.pieces(Piece::Bishop);
+
+    if (knights | bishops).popcnt() <= 1 {
+        return true;
+    }
+
+    knights == EMPTY && ((bishops & DARK_SQUARES) == EMPTY || (bishops & !DARK_SQUARES) == EMPTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess::{Board, BoardStatus};
+
+    use super::{is_repetition, is_fifty_moves, is_insufficient_material};
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    #[test]
+    fn repetition_four_plies_back() {
+        let history = [(1, 0), (2, 1), (3, 2), (4, 3), (1, 4)];
+
+        assert!(is_repetition(&history));
+        assert!(!is_repetition(&history[..4]));
+    }
+
+    #[test]
+    fn repetition_behind_an_irreversible_move() {
+        // The hash matches, but a capture or pawn move came in between
+        let history = [(1, 5), (2, 6), (3, 0), (4, 1), (1, 2)];
+
+        assert!(!is_repetition(&history));
+    }
+
+    #[test]
+    fn repetition_needs_the_same_side_to_move() {
+        // An odd number of plies back, the other side was to move
+        let history = [(1, 0), (2, 1), (3, 2), (1, 3)];
+
+        assert!(!is_repetition(&history));
+    }
+
+    #[test]
+    fn fifty_moves_after_100_plies() {
+        let kings = board("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+        assert!(!is_fifty_moves(&kings, &[(1, 99)]));
+        assert!(is_fifty_moves(&kings, &[(1, 100)]));
+    }
+
+    #[test]
+    fn mate_on_the_100th_ply_still_counts() {
+        let mated = board("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+
+        assert_eq!(mated.status(), BoardStatus::Checkmate);
+        assert!(!is_fifty_moves(&mated, &[(1, 100)]));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let cases = [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KN2 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/4KB2 w - - 0 1", true),
+            // Both bishops on dark squares
+            ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            // One on a light square, one on a dark one
+            ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+            // Two knights can't force mate, but can still give one
+            ("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+        ];
+
+        for (fen, expected) in cases {
+            assert_eq!(is_insufficient_material(&board(fen)), expected, "{fen}");
+        }
+    }
+}
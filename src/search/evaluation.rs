@@ -4,19 +4,12 @@ use chess::{
     Piece,
     Square,
     MoveGen,
-    Color,
-    BitBoard,
     ALL_FILES,
     ALL_RANKS,
 };
 
+use crate::search::score::Score;
 use crate::constants::{
-    CHECKMATE,
-    PAWN,
-    BISHOP,
-    KNIGHT,
-    ROOK,
-    QUEEN,
     CENTER_DIST,
     PAWNS,
     BISHOPS,
@@ -27,14 +20,14 @@ use crate::constants::{
     value,
 };
 
-pub fn evaluate(b: &Board) -> f32 {
+pub fn evaluate(b: &Board) -> Score {
     let mut score = 0f32;
     
     // Mate/stalemate
     if b.status() == BoardStatus::Checkmate {
-        return -CHECKMATE;
+        return -Score::MATE;
     } else if b.status() == BoardStatus::Stalemate {
-        return 0f32;
+        return Score::DRAW;
     }
 
     // Convenience variables
@@ -270,7 +263,7 @@ pub fn evaluate(b: &Board) -> f32 {
     
     // Mobility
     // Their
-    score -= MoveGen::new_legal(&b).count() as f32 * 0.1;
+    score -= MoveGen::new_legal(b).count() as f32 * 0.1;
     
     // Ours
     if let Some(nb) = b.null_move() {
@@ -279,5 +272,7 @@ pub fn evaluate(b: &Board) -> f32 {
     
     // Board control = pieces square + attacked squares
     
-    score
+    // Everything above scores the side that just moved, but the search wants
+    // the point of view of the side to move
+    Score::from_pawns(-score)
 }
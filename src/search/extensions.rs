@@ -0,0 +1,54 @@
+use chess::{Board, ChessMove, Color, Piece, Rank, EMPTY};
+
+pub const DEFAULT_MAX_EXTENSIONS: u8 = 16;
+pub const MAX_EXTENSIONS: u8 = 64;
+
+/// Which moves are searched one ply deeper than the rest, so that forcing
+/// lines are followed further than quiet ones.
+#[derive(Clone, Copy, Debug)]
+pub struct ExtensionPolicy {
+    // Moves that give check
+    pub check: bool,
+    // The only legal move in a position
+    pub single_reply: bool,
+    // Pawn pushes to the seventh rank, where every pawn is passed
+    pub passed_pawn: bool,
+    // Hash moves much better than every alternative
+    pub singular: bool,
+    // Extra plies allowed along a single path from the root
+    pub max_per_path: u8,
+}
+
+impl Default for ExtensionPolicy {
+    fn default() -> ExtensionPolicy {
+        ExtensionPolicy {
+            check: true,
+            single_reply: true,
+            passed_pawn: true,
+            singular: true,
+            max_per_path: DEFAULT_MAX_EXTENSIONS,
+        }
+    }
+}
+
+impl ExtensionPolicy {
+    /// Plies to extend the search of `m`, played on `board` to reach `after`,
+    /// before the per-path cap. `is_singular` tells whether `m` was found
+    /// singular.
+    pub fn extension(&self, board: &Board, m: ChessMove, after: &Board, is_single_reply: bool, is_singular: bool) -> u8 {
+        let gives_check = *after.checkers() != EMPTY;
+
+        let extend = (self.check && gives_check)
+            || (self.single_reply && is_single_reply)
+            || (self.passed_pawn && is_pawn_to_seventh(board, m))
+            || (self.singular && is_singular);
+
+        extend as u8
+    }
+}
+
+fn is_pawn_to_seventh(board: &Board, m: ChessMove) -> bool {
+    let seventh = if board.side_to_move() == Color::White { Rank::Seventh } else { Rank::Second };
+
+    board.piece_on(m.get_source()) == Some(Piece::Pawn) && m.get_dest().get_rank() == seventh
+}
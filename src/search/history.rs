@@ -0,0 +1,37 @@
+use chess::ChessMove;
+
+// Scores are halved once any of them grows past this
+const MAX_HISTORY: i32 = 1 << 16;
+
+/// How often each quiet move, by source and destination square, caused a
+/// beta cutoff, weighted by the depth it happened at.
+pub struct HistoryTable {
+    scores: Vec<[i32; 64]>,
+}
+
+impl HistoryTable {
+    pub fn new() -> HistoryTable {
+        HistoryTable {
+            scores: vec![[0; 64]; 64],
+        }
+    }
+
+    pub fn get(&self, m: ChessMove) -> i32 {
+        self.scores[m.get_source().to_index()][m.get_dest().to_index()]
+    }
+
+    /// Rewards `m` for a cutoff at `depth`, deeper ones counting more.
+    pub fn reward(&mut self, m: ChessMove, depth: u8) {
+        let score = &mut self.scores[m.get_source().to_index()][m.get_dest().to_index()];
+        *score += depth as i32 * depth as i32;
+
+        // Old cutoffs fade so that recent ones matter more
+        if *score > MAX_HISTORY {
+            for row in self.scores.iter_mut() {
+                for s in row.iter_mut() {
+                    *s /= 2;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use chess::{Board, ChessMove, MoveGen};
+
+
+use crate::search::score::Score;
+use crate::search::negamax::negamax;
+use crate::search::searcher::Searcher;
+use crate::search::transposition::{TT, HashTable};
+use crate::search::limits::MAX_PLY;
+use crate::uci;
+
+// Iterations before the previous score is trusted to center the window
+const ASPIRATION_DEPTH: u8 = 4;
+// Initial half-width of the window, doubled on every failure
+const ASPIRATION_WINDOW: Score = Score::cp(50);
+
+/// Searches `board` at depth 1, 2, 3... until a limit is hit and returns the
+/// principal variation of the last completed iteration.
+///
+/// An interrupted iteration is only used if no iteration has completed yet,
+/// and any legal move is returned as a last resort. The line is empty only
+/// when there are no legal moves.
+pub fn iterative_deepening(searcher: &mut Searcher, board: &Board, table: &TT, hash_table: &mut HashTable) -> Vec<ChessMove> {
+    let mut pv: Vec<ChessMove> = vec![];
+    let mut root_scores: Vec<(ChessMove, Score)> = vec![];
+    let hash = table.hash(board);
+    let mut last_score = None;
+
+    hash_table.new_search();
+
+    for depth in 1..=searcher.limits.max_depth() {
+        searcher.seldepth = 0;
+
+        let (best_move, score) = aspiration(searcher, board, depth, last_score, hash, table, hash_table);
+        let line = root_line(searcher, board, best_move, table, hash_table);
+
+        if searcher.is_interruption() {
+            if pv.is_empty() {
+                pv = line;
+            }
+            break;
+        }
+
+        pv = line;
+        last_score = Some(score);
+        root_scores = searcher.root_scores.clone();
+        uci::info(depth, searcher.seldepth, score, searcher.nodes, searcher.start.elapsed(), hash_table.hashfull(), &pv);
+
+        // Not enough time left to finish another iteration
+        if searcher.is_soft_timeout() {
+            break;
+        }
+    }
+
+    if pv.is_empty() {
+        pv.extend(MoveGen::new_legal(board).next());
+    }
+
+    // Weakened play trades the best move for a close enough one
+    if searcher.strength.is_randomized() {
+        let strength = searcher.strength;
+
+        if let Some(m) = strength.pick(&root_scores, &mut searcher.rng) {
+            if pv.first() != Some(&m) {
+                pv = vec![m];
+            }
+        }
+    }
+
+    pv
+}
+
+/// Searches the root in a narrow window around the previous iteration's
+/// score, widening it on the failing side until the score falls inside.
+fn aspiration(searcher: &mut Searcher, board: &Board, depth: u8, last_score: Option<Score>, hash: u64, table: &TT, hash_table: &mut HashTable) -> (Option<ChessMove>, Score) {
+    // Shallow scores are too unstable and mate scores too far apart
+    let Some(last_score) = last_score.filter(|s| depth >= ASPIRATION_DEPTH && !s.is_mate()) else {
+        return negamax(searcher, board, depth, -Score::INFINITE, Score::INFINITE, 0, hash, table, hash_table);
+    };
+
+    let mut delta = ASPIRATION_WINDOW;
+    let mut alpha = (last_score - delta).max(-Score::INFINITE);
+    let mut beta = (last_score + delta).min(Score::INFINITE);
+
+    loop {
+        let (best_move, score) = negamax(searcher, board, depth, alpha, beta, 0, hash, table, hash_table);
+
+        if searcher.is_interruption() {
+            return (best_move, score);
+        }
+
+        if score <= alpha {
+            alpha = (score - delta).max(-Score::INFINITE);
+        } else if score >= beta {
+            beta = (score + delta).min(Score::INFINITE);
+        } else {
+            return (best_move, score);
+        }
+
+        delta = delta + delta;
+    }
+}
+
+/// The PV table's line, as long as it starts with the move the root picked,
+/// completed with hash moves where transposition cutoffs truncated it.
+fn root_line(searcher: &Searcher, board: &Board, best_move: Option<ChessMove>, table: &TT, hash_table: &HashTable) -> Vec<ChessMove> {
+    let mut line = searcher.pv.line();
+
+    if line.first() != best_move.as_ref() {
+        line = best_move.into_iter().collect();
+    }
+
+    let mut board = line.iter().fold(*board, |b, m| b.make_move_new(*m));
+    let mut seen = vec![];
+
+    while line.len() < MAX_PLY {
+        let hash = table.hash(&board);
+
+        // Following hash moves can cycle
+        if seen.contains(&hash) {
+            break;
+        }
+        seen.push(hash);
+
+        match hash_table.get(hash).and_then(|e| e.best_move) {
+            Some(m) if board.legal(m) => {
+                board = board.make_move_new(m);
+                line.push(m);
+            },
+            _ => break,
+        }
+    }
+
+    line
+}
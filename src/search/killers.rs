@@ -0,0 +1,42 @@
+use chess::ChessMove;
+
+use crate::search::limits::MAX_PLY;
+
+// Killer moves remembered per ply
+const KILLERS_PER_PLY: usize = 2;
+
+/// Quiet moves that caused a beta cutoff at the same ply elsewhere in the
+/// tree, likely to refute sibling positions too.
+pub struct Killers {
+    moves: [[Option<ChessMove>; KILLERS_PER_PLY]; MAX_PLY],
+}
+
+impl Killers {
+    pub fn new() -> Killers {
+        Killers {
+            moves: [[None; KILLERS_PER_PLY]; MAX_PLY],
+        }
+    }
+
+    /// The killers at `ply`, most recent first.
+    pub fn get(&self, ply: usize) -> Vec<ChessMove> {
+        match self.moves.get(ply) {
+            Some(killers) => killers.iter().filter_map(|m| *m).collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn contains(&self, ply: usize, m: ChessMove) -> bool {
+        self.moves.get(ply).is_some_and(|killers| killers.contains(&Some(m)))
+    }
+
+    /// Remembers `m` as the latest killer at `ply`, forgetting the oldest.
+    pub fn insert(&mut self, ply: usize, m: ChessMove) {
+        if ply >= MAX_PLY || self.contains(ply, m) {
+            return;
+        }
+
+        self.moves[ply].rotate_right(1);
+        self.moves[ply][0] = Some(m);
+    }
+}
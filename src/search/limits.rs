@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+// Depth used when `go` doesn't bound the search in any other way
+pub const DEFAULT_DEPTH: u8 = 7;
+pub const MAX_DEPTH: u8 = 64;
+// Deepest ply any line of the search can reach
+pub const MAX_PLY: usize = 128;
+
+/// Search bounds given by the UCI `go` command.
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub mate: Option<u8>,
+    pub infinite: bool,
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+}
+
+impl Limits {
+    /// Maximum depth the search is allowed to reach.
+    pub fn max_depth(&self) -> u8 {
+        if let Some(depth) = self.depth {
+            return depth.clamp(1, MAX_DEPTH);
+        }
+
+        // A mate in N moves needs 2N - 1 plies
+        if let Some(mate) = self.mate {
+            return mate.saturating_mul(2).saturating_sub(1).clamp(1, MAX_DEPTH);
+        }
+
+        // Nodes and time bound the search on their own
+        let is_bounded = self.nodes.is_some() || self.movetime.is_some() || self.wtime.is_some() || self.btime.is_some();
+
+        if self.infinite || is_bounded {
+            MAX_DEPTH
+        } else {
+            DEFAULT_DEPTH
+        }
+    }
+
+    /// Whether the search must stop after visiting `nodes` nodes.
+    pub fn nodes_exceeded(&self, nodes: u64) -> bool {
+        !self.infinite && self.nodes.is_some_and(|max_nodes| nodes >= max_nodes)
+    }
+}
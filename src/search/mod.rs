@@ -1,4 +1,18 @@
 pub mod negamax;
 pub mod evaluation;
 pub mod ordering;
-pub mod transposition;
\ No newline at end of file
+pub mod transposition;
+pub mod limits;
+pub mod time;
+pub mod searcher;
+pub mod iterative;
+pub mod pv;
+pub mod quiescence;
+pub mod draw;
+pub mod strength;
+pub mod score;
+pub mod killers;
+pub mod history;
+pub mod extensions;
+pub mod pruning;
+pub mod bench;
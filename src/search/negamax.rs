@@ -3,34 +3,63 @@ use chess::{
     MoveGen,
     ChessMove,
     BoardStatus,
-    Color,
+    Piece,
+    EMPTY,
 };
 
-use std::collections::HashMap;
 use rand::prelude::*;
 
-use crate::engine::Weakfish;
-use crate::search::transposition::{TT, TableEntry};
+use crate::search::searcher::Searcher;
+use crate::uci;
+use crate::search::transposition::{TT, HashTable, TableEntry, Bound};
+use crate::search::quiescence::quiescence;
+use crate::search::draw::is_draw;
+use crate::search::ordering::{order_moves, is_quiet};
+use crate::search::limits::MAX_PLY;
+use crate::search::pruning::{REVERSE_FUTILITY_DEPTH, FUTILITY_DEPTH, RAZORING_DEPTH};
+use crate::search::score::Score;
 use crate::search::evaluation::evaluate;
-use crate::search::ordering::order_moves;
-use crate::constants::{CHECKMATE};
 
-pub fn negamax(weakfish: &Weakfish, board: &Board, depth: u8, mut alpha: f32, mut beta: f32, turn_multiplier: f32, ply: u8, table: &TT, hashmap: &mut HashMap<u64, TableEntry>) -> (Option<ChessMove>, f32) {
-    if weakfish.is_interruption {
-        return (None, 0f32);
+// Shallowest depth worth a null move search
+const NULL_MOVE_DEPTH: u8 = 3;
+// Depths from which a null move cutoff is confirmed by a real search
+const VERIFICATION_DEPTH: u8 = 7;
+// Shallowest depth worth a search for singular moves
+const SINGULAR_DEPTH: u8 = 8;
+// Centipawns per ply of depth the alternatives must stay under the hash move
+const SINGULAR_MARGIN: i32 = 2;
+// Moves searched at full depth before reducing the rest
+const LMR_MOVES: usize = 3;
+// Shallowest depth where late moves are reduced
+const LMR_DEPTH: u8 = 3;
+// Deepest depth where late quiet moves are pruned outright
+const LMP_DEPTH: u8 = 3;
+
+#[allow(clippy::too_many_arguments)]
+pub fn negamax(searcher: &mut Searcher, board: &Board, depth: u8, mut alpha: Score, mut beta: Score, ply: u8, hash: u64, table: &TT, hash_table: &mut HashTable) -> (Option<ChessMove>, Score) {
+    searcher.nodes += 1;
+    searcher.seldepth = searcher.seldepth.max(ply);
+    searcher.pv.clear(ply as usize);
+    searcher.check_limits();
+
+    if searcher.is_interruption() {
+        return (None, Score::DRAW);
     }
+
+    let after_null_move = std::mem::replace(&mut searcher.after_null_move, false);
+    let excluded_move = searcher.excluded_move.take();
     
     // Mate distance pruning
-    let mated = CHECKMATE - ply as f32;
+    let mating = Score::mate_in(ply);
 
-    if mated < beta {
-        beta = mated;
-        if alpha >= mated {
-            return (None, mated);
+    if mating < beta {
+        beta = mating;
+        if alpha >= mating {
+            return (None, mating);
         }
     }
 
-    let mated = -CHECKMATE + ply as f32;
+    let mated = Score::mated_in(ply);
 
     if mated > alpha {
         alpha = mated;
@@ -39,40 +68,212 @@ pub fn negamax(weakfish: &Weakfish, board: &Board, depth: u8, mut alpha: f32, mu
         }
     }
     
+    // Repetitions, fifty-move rule and dead positions
+    if ply > 0 && is_draw(board, &searcher.history) {
+        return (None, searcher.draw_score(board.side_to_move()));
+    }
+
+    // Extensions can't take the search past the end of the tables
+    if ply as usize >= MAX_PLY - 1 {
+        return (None, evaluate(board));
+    }
+    
     //Lookup in transposition table
-    let hash = table.hash(board);
-    let mut score = 0f32;
+    debug_assert_eq!(hash, table.hash(board), "incremental hash diverged from a full rehash");
+    let alpha_orig = alpha;
+    let entry = hash_table.get(hash).copied().map(|e| TableEntry { score: e.score.adjust_from_tt(ply), ..e });
+    let hash_move = entry.and_then(|e| e.best_move);
+
+    // The root always searches so that it has a move to return, and only
+    // entries searched at least as deep as this node can be trusted. A search
+    // without one of the moves can't use the full node's score either
+    if ply > 0 && excluded_move.is_none() {
+        if let Some(entry) = entry.filter(|e| e.depth >= depth) {
+            // Bounds only cut off, narrowing the window with them would make
+            // the score stored below look exact when it isn't
+            let is_cutoff = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => entry.score >= beta,
+                Bound::Upper => entry.score <= alpha,
+            };
+
+            if is_cutoff {
+                return (None, entry.score);
+            }
+        }
+    }
 
-    if let Some(entry) = hashmap.get(&hash) {
-        return (None, entry.score);
+    // Getting mated later is better, as is mating sooner
+    match board.status() {
+        BoardStatus::Checkmate => return (None, Score::mated_in(ply)),
+        BoardStatus::Stalemate => return (None, searcher.draw_score(board.side_to_move())),
+        BoardStatus::Ongoing => {},
     }
 
-    if depth == 0 || board.status() == BoardStatus::Checkmate || board.status() == BoardStatus::Stalemate {
-        return (None, evaluate(&board) + 1f32 - ply as f32);
+    if depth == 0 {
+        return (None, quiescence(searcher, board, alpha, beta, ply, 0));
+    }
+
+    let in_check = *board.checkers() != EMPTY;
+    let is_pv = beta > alpha + Score::cp(1);
+
+    // Forward pruning trusts the static eval, which means nothing in check
+    let static_eval = if in_check { None } else { Some(evaluate(board)) };
+    let is_prunable = ply > 0 && !is_pv && excluded_move.is_none();
+    let margins = searcher.margins;
+
+    if let Some(eval) = static_eval.filter(|_| is_prunable) {
+        // Reverse futility: too far above beta for the opponent to catch up
+        if depth <= REVERSE_FUTILITY_DEPTH && !beta.is_mate() && eval - margins.reverse_futility(depth) >= beta {
+            return (None, eval);
+        }
+
+        // Razoring: too far below alpha for a quiet move to help, so only
+        // tactics are worth a look
+        if depth <= RAZORING_DEPTH && !alpha.is_mate() && eval + margins.razoring(depth) < alpha {
+            let score = quiescence(searcher, board, alpha, beta, ply, 0);
+
+            if score <= alpha {
+                return (None, score);
+            }
+        }
+    }
+
+    if is_prunable && !after_null_move && depth >= NULL_MOVE_DEPTH && is_null_move_safe(board) && !beta.is_mate() && static_eval.is_some_and(|eval| eval >= beta) {
+        if let Some(score) = null_move(searcher, board, depth, beta, ply, hash, table, hash_table) {
+            return (None, score);
+        }
     }
     
-    let movegen = MoveGen::new_legal(&board);
-    let movegen = order_moves(board, movegen);
+    let killers = searcher.killers.get(ply as usize);
+    let movegen = MoveGen::new_legal(board);
+    let mut movegen = order_moves(board, movegen, hash_move, &killers, &searcher.history_table);
+    let is_single_reply = movegen.len() == 1;
+
+    movegen.retain(|m| Some(*m) != excluded_move);
+
+    // Singular extension: the hash move is the only good one if every other
+    // move fails low against a margin below its score
+    let is_singular = match (entry, hash_move) {
+        (Some(entry), Some(hash_move)) if ply > 0
+            && excluded_move.is_none()
+            && searcher.extensions.singular
+            && depth >= SINGULAR_DEPTH
+            && entry.bound != Bound::Upper
+            && entry.depth + 3 >= depth
+            && !entry.score.is_mate() => {
+            is_singular(searcher, board, depth, entry.score, hash_move, ply, hash, table, hash_table)
+        },
+        _ => false,
+    };
     
     let mut best_so_far: Option<ChessMove> = None;
-    let mut max_score = -CHECKMATE;
+    let mut max_score = -Score::INFINITE;
+
+    // Weakened play picks among all root moves, so each needs an exact score
+    let is_scoring_root = ply == 0 && searcher.strength.is_randomized();
+
+    if ply == 0 {
+        searcher.root_scores.clear();
+    }
+
+    for (i, m) in movegen.into_iter().enumerate() {
+        if ply == 0 && searcher.is_reporting_current_move() {
+            uci::current_move(m, i + 1);
+        }
 
-    for m in movegen {
         let b = board.make_move_new(m);
-        score = -negamax(weakfish, &b, depth - 1, -beta, -alpha, -turn_multiplier, ply + 1, table, hashmap).1;
+        let child_hash = table.update_hash(hash, board, m, &b);
+        let is_irreversible = board.piece_on(m.get_source()) == Some(Piece::Pawn) || board.piece_on(m.get_dest()).is_some();
+
+        let path_extension = searcher.extended[ply as usize];
+        let extension = if path_extension < searcher.extensions.max_per_path {
+            searcher.extensions.extension(board, m, &b, is_single_reply, is_singular && Some(m) == hash_move)
+        } else {
+            0
+        };
+        let child_depth = depth - 1 + extension;
+        searcher.extended[ply as usize + 1] = path_extension + extension;
+
+        // Quiet moves that don't check and aren't killers are the likeliest
+        // to be bad this late in the ordering
+        let is_late_quiet = !is_scoring_root
+            && extension == 0
+            && !in_check
+            && is_quiet(board, m)
+            && *b.checkers() == EMPTY
+            && !killers.contains(&m);
+
+        // Late move pruning, as long as a move already escaped being mated
+        if ply > 0 && is_late_quiet && depth <= LMP_DEPTH && i >= lmp_moves(depth) && max_score > Score::mated_in(MAX_PLY as u8) {
+            continue;
+        }
+
+        // Futility pruning: quiet moves can't make up for the static eval
+        // being this far below alpha
+        if is_prunable
+            && is_late_quiet
+            && depth <= FUTILITY_DEPTH
+            && max_score > Score::mated_in(MAX_PLY as u8)
+            && !alpha.is_mate()
+            && static_eval.is_some_and(|eval| eval + margins.futility(depth) <= alpha)
+        {
+            continue;
+        }
+
+        let reduction = if is_late_quiet && i >= LMR_MOVES && depth >= LMR_DEPTH {
+            lmr(depth, i, searcher.history_table.get(m))
+        } else {
+            0
+        };
+
+        searcher.push(child_hash, is_irreversible);
+
+        let score = if is_scoring_root {
+            -negamax(searcher, &b, child_depth, -Score::INFINITE, Score::INFINITE, ply + 1, child_hash, table, hash_table).1
+        } else if i == 0 {
+            -negamax(searcher, &b, child_depth, -beta, -alpha, ply + 1, child_hash, table, hash_table).1
+        } else {
+            // Principal variation search: later moves only have to prove they
+            // are no better than the first one, unless they turn out to be
+            let null_window = alpha + Score::cp(1);
+            let mut s = -negamax(searcher, &b, child_depth - reduction, -null_window, -alpha, ply + 1, child_hash, table, hash_table).1;
+
+            // Late moves are searched shallower, unless they look good
+            if reduction > 0 && s > alpha {
+                s = -negamax(searcher, &b, child_depth, -null_window, -alpha, ply + 1, child_hash, table, hash_table).1;
+            }
+
+            if s > alpha && s < beta {
+                -negamax(searcher, &b, child_depth, -beta, -alpha, ply + 1, child_hash, table, hash_table).1
+            } else {
+                s
+            }
+        };
+
+        searcher.pop();
+
+        if ply == 0 {
+            searcher.root_scores.push((m, score));
+        }
 
         if score > max_score {
             best_so_far = Some(m);
             max_score = score;
         }
 
-        if depth == 7 {
-            println!("{} {:0.1} | best {:0.1} ({})", m, score, max_score, if let Some(x) = best_so_far { x.to_string() } else { "None".to_string() });
+        if score > alpha {
+            searcher.pv.update(ply as usize, m);
         }
 
         alpha = if alpha >= score { alpha } else { score };
         
         if alpha >= beta {
+            // Remember quiet refutations to try them early in siblings
+            if is_quiet(board, m) {
+                searcher.killers.insert(ply as usize, m);
+                searcher.history_table.reward(m, depth);
+            }
             break;
         }
         // } else {
@@ -81,16 +282,204 @@ pub fn negamax(weakfish: &Weakfish, board: &Board, depth: u8, mut alpha: f32, mu
     }
     
     // Get last move if all moves are equivalent
-    if best_so_far == None {
+    if best_so_far.is_none() {
         let mut moves: Vec<ChessMove> = MoveGen::new_legal(board).collect();
-        moves.shuffle(&mut rand::thread_rng());
+        moves.shuffle(&mut searcher.rng);
         best_so_far = Some(*moves.last().unwrap());
     }
     
-    hashmap.insert(hash, TableEntry { depth: depth, score: max_score, age: 0u8 });
+    // Don't store scores from an aborted search, or from one that skipped a move
+    if searcher.is_interruption() || excluded_move.is_some() {
+        return (best_so_far, max_score);
+    }
+    
+    // A score outside the original window only bounds the real one
+    let bound = if max_score <= alpha_orig {
+        Bound::Upper
+    } else if max_score >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+
+    // No move stood out when failing low, keep the previous one
+    let best_move = if bound == Bound::Upper { hash_move } else { best_so_far };
+
+    hash_table.insert(hash, TableEntry::new(depth, max_score.to_tt(ply), hash_table.age(), bound, best_move));
 
     // if best_so_far != None && ply == 1 {
     //     println!("Ponder {} {:0.1}", best_so_far.unwrap(), max_score);
     // }
     (best_so_far, max_score)
-}
\ No newline at end of file
+}
+
+/// Null move pruning: if passing the turn still fails high at a reduced depth,
+/// a real move almost surely would too. Returns the cutoff score, if any.
+#[allow(clippy::too_many_arguments)]
+fn null_move(searcher: &mut Searcher, board: &Board, depth: u8, beta: Score, ply: u8, hash: u64, table: &TT, hash_table: &mut HashTable) -> Option<Score> {
+    let b = board.null_move()?;
+    let child_hash = table.null_move_hash(hash, board, &b);
+
+    // Deeper nodes can afford a bigger reduction
+    let reduction = if depth > 6 { 3 } else { 2 };
+    let reduced = depth.saturating_sub(reduction + 1);
+
+    searcher.push(child_hash, true);
+    searcher.after_null_move = true;
+    searcher.extended[ply as usize + 1] = searcher.extended[ply as usize];
+    let score = -negamax(searcher, &b, reduced, -beta, -beta + Score::cp(1), ply + 1, child_hash, table, hash_table).1;
+    searcher.pop();
+
+    if searcher.is_interruption() || score < beta {
+        return None;
+    }
+
+    // Passing the turn can't prove a mate
+    let score = if score.is_mate() { beta } else { score };
+
+    if depth < VERIFICATION_DEPTH {
+        return Some(score);
+    }
+
+    // Zugzwang can still fool the null move, so search this node for real
+    // at the reduced depth, without another null move
+    searcher.after_null_move = true;
+    let verified = negamax(searcher, board, reduced, beta - Score::cp(1), beta, ply, hash, table, hash_table).1;
+
+    (!searcher.is_interruption() && verified >= beta).then_some(score)
+}
+
+/// Whether every move but `hash_move` fails low against a margin below
+/// `tt_score`, searched at half depth.
+#[allow(clippy::too_many_arguments)]
+fn is_singular(searcher: &mut Searcher, board: &Board, depth: u8, tt_score: Score, hash_move: ChessMove, ply: u8, hash: u64, table: &TT, hash_table: &mut HashTable) -> bool {
+    let singular_beta = tt_score - Score::cp(SINGULAR_MARGIN * depth as i32);
+
+    searcher.excluded_move = Some(hash_move);
+    let score = negamax(searcher, board, depth / 2, singular_beta - Score::cp(1), singular_beta, ply, hash, table, hash_table).1;
+
+    !searcher.is_interruption() && score < singular_beta
+}
+
+/// Plies to cut from the search of the `index`th move, more for later moves
+/// at deeper nodes, less for moves with a history of cutoffs.
+fn lmr(depth: u8, index: usize, history: i32) -> u8 {
+    let reduction = 0.75 + (depth as f32).ln() * (index as f32).ln() / 2.25;
+    let reduction = (reduction as u8).saturating_sub((history > 0) as u8);
+
+    // Always leave at least one ply
+    reduction.min(depth - 2)
+}
+
+/// Quiet moves searched at `depth` before the rest are pruned.
+fn lmp_moves(depth: u8) -> usize {
+    3 + 2 * depth as usize * depth as usize
+}
+
+/// Passing the turn is only sound when not in check, and only likely to be a
+/// worse option than moving when there are pieces besides king and pawns.
+fn is_null_move_safe(board: &Board) -> bool {
+    let ours = *board.color_combined(board.side_to_move());
+    let pawns_and_king = *board.pieces(Piece::Pawn) | *board.pieces(Piece::King);
+
+    *board.checkers() == EMPTY && ours & !pawns_and_king != EMPTY
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use chess::{Board, BoardStatus, ChessMove, MoveGen};
+
+    use super::negamax;
+    use crate::search::limits::Limits;
+    use crate::search::score::Score;
+    use crate::search::searcher::Searcher;
+    use crate::search::transposition::{TT, HashTable};
+
+    /// Deepens up to `depth` like the engine does and returns the root's
+    /// best move and score.
+    fn search(fen: &str, depth: u8) -> (Option<ChessMove>, Score) {
+        let board = Board::from_str(fen).unwrap();
+        let table = TT::from_seed(0);
+        let mut hash_table = HashTable::new(1);
+        let hash = table.hash(&board);
+
+        let mut searcher = Searcher::new(Limits::default(), board.side_to_move(), Duration::ZERO, 0, Arc::new(AtomicBool::new(false)));
+        searcher.history = vec![(hash, 0)];
+
+        let mut result = (None, Score::DRAW);
+
+        for d in 1..=depth {
+            hash_table.new_search();
+            result = negamax(&mut searcher, &board, d, -Score::INFINITE, Score::INFINITE, 0, hash, &table, &mut hash_table);
+        }
+
+        result
+    }
+
+    /// Whether the side to move can force mate within `moves` moves.
+    fn can_mate_in(board: &Board, moves: u8) -> bool {
+        moves > 0 && MoveGen::new_legal(board).any(|m| {
+            let b = board.make_move_new(m);
+
+            match b.status() {
+                BoardStatus::Checkmate => true,
+                BoardStatus::Stalemate => false,
+                BoardStatus::Ongoing => MoveGen::new_legal(&b).all(|r| can_mate_in(&b.make_move_new(r), moves - 1)),
+            }
+        })
+    }
+
+    /// Checks that mate in `moves` is found, starting with `key`, and that no
+    /// faster mate exists.
+    fn assert_mate(fen: &str, moves: i32, key: &str) {
+        let (best, score) = search(fen, 2 * moves as u8 + 1);
+
+        assert_eq!(score.mate_moves(), Some(moves), "{}", fen);
+        assert_eq!(best, Some(ChessMove::from_str(key).unwrap()), "{}", fen);
+        assert!(!can_mate_in(&Board::from_str(fen).unwrap(), moves as u8 - 1), "{} has a faster mate", fen);
+    }
+
+    #[test]
+    fn mate_in_one() {
+        assert_mate("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1", 1, "a1a8");
+    }
+
+    #[test]
+    fn mate_in_one_over_longer_mates() {
+        // Rh7 and other rook moves mate in two
+        let fen = "k7/8/1K6/8/8/8/8/7R w - - 0 1";
+        assert!(can_mate_in(&Board::from_str(fen).unwrap(), 2));
+
+        assert_mate(fen, 1, "h1h8");
+    }
+
+    #[test]
+    fn mate_in_two() {
+        assert_mate("r2qkb1r/pp2nppp/3p4/2pNN1B1/2BnP3/3P4/PPP2PPP/R2bK2R w KQkq - 1 1", 2, "d5f6");
+    }
+
+    #[test]
+    fn mate_in_three() {
+        assert_mate("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq - 0 1", 3, "f8c5");
+    }
+
+    #[test]
+    fn mated_side_delays_the_mate() {
+        let (_, score) = search("8/8/8/8/8/5k2/8/1r4K1 w - - 0 1", 6);
+
+        assert_eq!(score.mate_moves(), Some(-2));
+    }
+
+    #[test]
+    fn checkmated_root_scores_mated_now() {
+        let (best, score) = search("k7/8/8/8/8/8/8/RR4K1 b - - 0 1", 1);
+
+        assert_eq!(best, None);
+        assert_eq!(score, Score::mated_in(0));
+    }
+}
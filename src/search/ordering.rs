@@ -2,28 +2,13 @@ use chess::{
     Board,
     MoveGen,
     ChessMove,
-    Color,
-    BitBoard,
     BoardStatus,
-    Square,
     Piece,
     EMPTY,
     ALL_PIECES,
 };
 
-use crate::constants::{
-    CHECKMATE,
-    QUEEN,
-    ROOK,
-    BISHOP,
-    KNIGHT,
-    PAWN,
-};
-
-use crate::search::evaluation;
-use crate::constants::value;
-
-use rand::prelude::*;
+use crate::search::history::HistoryTable;
 
 fn mvvlva(board: &Board, movegen: &mut MoveGen) -> Vec<ChessMove> {
     let mut moves = vec![];
@@ -40,7 +25,30 @@ fn mvvlva(board: &Board, movegen: &mut MoveGen) -> Vec<ChessMove> {
     moves
 }
 
-pub fn order_moves(board: &Board, mut movegen: MoveGen) -> Vec<ChessMove> {
+/// Captures in MVV-LVA order followed by quiet queen promotions, the only
+/// moves the quiescence search looks at.
+pub fn order_captures(board: &Board, mut movegen: MoveGen) -> Vec<ChessMove> {
+    let mut moves: Vec<ChessMove> = vec![];
+    
+    movegen.set_iterator_mask(*board.color_combined(!board.side_to_move()));
+    moves.append(&mut mvvlva(board, &mut movegen));
+    
+    // Quiet pushes to the last rank
+    let last_rank = chess::get_rank(board.side_to_move().to_their_backrank());
+    movegen.set_iterator_mask(last_rank & !*board.combined());
+    
+    for m in &mut movegen {
+        if m.get_promotion() == Some(Piece::Queen) {
+            moves.push(m);
+        }
+    }
+    
+    moves
+}
+
+/// Hash move first, then captures, killers and the other quiet moves by
+/// history score.
+pub fn order_moves(board: &Board, mut movegen: MoveGen, hash_move: Option<ChessMove>, killers: &[ChessMove], history: &HistoryTable) -> Vec<ChessMove> {
     let mut moves: Vec<ChessMove> = vec![];
     
     // Captures
@@ -55,15 +63,37 @@ pub fn order_moves(board: &Board, mut movegen: MoveGen) -> Vec<ChessMove> {
         moves.append(&mut mvvlva(board, &mut movegen));
     }
     
-    // Iterate over the rest of the moves
+    // Killers, as long as they're legal quiet moves here
     movegen.set_iterator_mask(!EMPTY);
-    
-    for m in &mut movegen {
-        moves.push(m);
+
+    for &killer in killers {
+        if Some(killer) != hash_move && is_quiet(board, killer) && board.legal(killer) && movegen.remove_move(killer) {
+            moves.push(killer);
+        }
+    }
+
+    // Iterate over the rest of the moves
+    let mut quiets: Vec<ChessMove> = (&mut movegen).collect();
+    quiets.sort_by_key(|m| -history.get(*m));
+    moves.append(&mut quiets);
+
+    // Hash move, as long as it's legal here. It's moved to the front rather
+    // than taken out of the generator, whose remove_move drops every
+    // promotion to the same square along with it
+    if let Some(hm) = hash_move.filter(|hm| board.legal(*hm)) {
+        moves.retain(|m| *m != hm);
+        moves.insert(0, hm);
     }
 
     // Shuffle
     // moves.shuffle(&mut rand::thread_rng());
     
     moves
-}
\ No newline at end of file
+}
+
+/// Whether `m` neither captures nor promotes.
+pub fn is_quiet(board: &Board, m: ChessMove) -> bool {
+    let is_en_passant = board.piece_on(m.get_source()) == Some(Piece::Pawn) && m.get_source().get_file() != m.get_dest().get_file();
+
+    board.piece_on(m.get_dest()).is_none() && m.get_promotion().is_none() && !is_en_passant
+}
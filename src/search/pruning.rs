@@ -0,0 +1,47 @@
+use crate::search::score::Score;
+
+pub const DEFAULT_REVERSE_FUTILITY_MARGIN: i32 = 120;
+pub const DEFAULT_FUTILITY_MARGIN: i32 = 150;
+pub const DEFAULT_RAZORING_MARGIN: i32 = 300;
+pub const MAX_PRUNING_MARGIN: i32 = 2000;
+
+// Deepest nodes each kind of pruning applies to
+pub const REVERSE_FUTILITY_DEPTH: u8 = 6;
+pub const FUTILITY_DEPTH: u8 = 3;
+pub const RAZORING_DEPTH: u8 = 2;
+
+/// How far, in centipawns per ply of remaining depth, the static evaluation
+/// has to be from the window before a node or move is pruned on its own word.
+#[derive(Clone, Copy, Debug)]
+pub struct PruningMargins {
+    // Above beta, to cut the node off right away
+    pub reverse_futility: i32,
+    // Below alpha, to skip quiet moves
+    pub futility: i32,
+    // Below alpha, to drop straight into quiescence
+    pub razoring: i32,
+}
+
+impl Default for PruningMargins {
+    fn default() -> PruningMargins {
+        PruningMargins {
+            reverse_futility: DEFAULT_REVERSE_FUTILITY_MARGIN,
+            futility: DEFAULT_FUTILITY_MARGIN,
+            razoring: DEFAULT_RAZORING_MARGIN,
+        }
+    }
+}
+
+impl PruningMargins {
+    pub fn reverse_futility(&self, depth: u8) -> Score {
+        Score::cp(self.reverse_futility * depth as i32)
+    }
+
+    pub fn futility(&self, depth: u8) -> Score {
+        Score::cp(self.futility * depth as i32)
+    }
+
+    pub fn razoring(&self, depth: u8) -> Score {
+        Score::cp(self.razoring * depth as i32)
+    }
+}
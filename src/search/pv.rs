@@ -0,0 +1,50 @@
+use chess::ChessMove;
+
+use crate::search::limits::MAX_PLY;
+
+/// Triangular principal variation table.
+///
+/// Row `ply` holds the best line found from that ply on; when a move raises
+/// alpha it is prepended to the line of the ply below.
+pub struct PvTable {
+    moves: Vec<[Option<ChessMove>; MAX_PLY]>,
+    length: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    pub fn new() -> PvTable {
+        PvTable {
+            moves: vec![[None; MAX_PLY]; MAX_PLY],
+            length: [0; MAX_PLY],
+        }
+    }
+
+    /// Empties the line at `ply`, done when a node is entered.
+    pub fn clear(&mut self, ply: usize) {
+        if ply < MAX_PLY {
+            self.length[ply] = ply;
+        }
+    }
+
+    /// Makes `m` followed by the line at `ply + 1` the line at `ply`.
+    pub fn update(&mut self, ply: usize, m: ChessMove) {
+        if ply >= MAX_PLY {
+            return;
+        }
+
+        self.moves[ply][ply] = Some(m);
+
+        let child_length = if ply + 1 < MAX_PLY { self.length[ply + 1].max(ply + 1) } else { ply + 1 };
+
+        for i in ply + 1..child_length {
+            self.moves[ply][i] = self.moves[ply + 1][i];
+        }
+
+        self.length[ply] = child_length;
+    }
+
+    /// The principal variation from the root.
+    pub fn line(&self) -> Vec<ChessMove> {
+        self.moves[0][..self.length[0]].iter().map_while(|m| *m).collect()
+    }
+}
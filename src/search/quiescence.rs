@@ -0,0 +1,112 @@
+use chess::{
+    Board,
+    MoveGen,
+    ChessMove,
+    BoardStatus,
+    EMPTY,
+};
+
+use crate::search::searcher::Searcher;
+use crate::search::evaluation::evaluate;
+use crate::search::ordering::{order_captures, order_moves};
+use crate::search::limits::MAX_PLY;
+use crate::search::score::Score;
+use crate::constants::value;
+
+// Also search quiet checks at the first quiescence ply
+const QUIESCENCE_CHECKS: bool = true;
+// Quiescence plies after which the static evaluation is trusted as is
+const MAX_QUIESCENCE_PLY: u8 = 16;
+// Captures that can't raise the score this close to alpha are skipped
+const DELTA_MARGIN: Score = Score::cp(200);
+
+/// Resolves captures and queen promotions past the search horizon so the
+/// static evaluation is only taken in quiet positions.
+///
+/// `qply` counts the plies since quiescence was entered. Positions in check
+/// search every evasion instead of standing pat.
+pub fn quiescence(searcher: &mut Searcher, board: &Board, mut alpha: Score, beta: Score, ply: u8, qply: u8) -> Score {
+    searcher.nodes += 1;
+    searcher.seldepth = searcher.seldepth.max(ply);
+    searcher.pv.clear(ply as usize);
+    searcher.check_limits();
+
+    if searcher.is_interruption() {
+        return Score::DRAW;
+    }
+
+    match board.status() {
+        BoardStatus::Checkmate => return Score::mated_in(ply),
+        BoardStatus::Stalemate => return searcher.draw_score(board.side_to_move()),
+        BoardStatus::Ongoing => {},
+    }
+
+    if qply >= MAX_QUIESCENCE_PLY || ply as usize >= MAX_PLY - 1 {
+        return evaluate(board);
+    }
+
+    let in_check = *board.checkers() != EMPTY;
+    let mut best = -Score::INFINITE;
+
+    let moves: Vec<ChessMove> = if in_check {
+        order_moves(board, MoveGen::new_legal(board), None, &[], &searcher.history_table)
+    } else {
+        // Stand pat: the side to move can usually do at least as well as now
+        best = evaluate(board);
+
+        if best >= beta {
+            return best;
+        }
+
+        if best > alpha {
+            alpha = best;
+        }
+
+        let mut moves = order_captures(board, MoveGen::new_legal(board));
+
+        if QUIESCENCE_CHECKS && qply == 0 {
+            moves.append(&mut quiet_checks(board));
+        }
+
+        moves
+    };
+
+    for m in moves {
+        // Delta pruning
+        if !in_check && m.get_promotion().is_none() {
+            if let Some(captured) = board.piece_on(m.get_dest()) {
+                if best + Score::from_pawns(value(captured)) + DELTA_MARGIN < alpha {
+                    continue;
+                }
+            }
+        }
+
+        let b = board.make_move_new(m);
+        let score = -quiescence(searcher, &b, -beta, -alpha, ply + 1, qply + 1);
+
+        if score > best {
+            best = score;
+        }
+
+        if score > alpha {
+            alpha = score;
+            searcher.pv.update(ply as usize, m);
+        }
+
+        if alpha >= beta {
+            break;
+        }
+    }
+
+    best
+}
+
+/// Non-capturing moves that give check.
+fn quiet_checks(board: &Board) -> Vec<ChessMove> {
+    let mut movegen = MoveGen::new_legal(board);
+    movegen.set_iterator_mask(!*board.combined());
+
+    movegen
+        .filter(|m| m.get_promotion().is_none() && *board.make_move_new(*m).checkers() != EMPTY)
+        .collect()
+}
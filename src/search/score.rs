@@ -0,0 +1,103 @@
+use std::ops::{Add, Neg, Sub};
+
+use crate::search::limits::MAX_PLY;
+
+/// A search score in centipawns from the point of view of the side to move.
+///
+/// Mates are encoded at the ends of the range: `MATE - n` is a mate delivered
+/// `n` plies from the root and `-MATE + n` is getting mated `n` plies from it,
+/// so that shorter mates always score higher.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Score(pub i32);
+
+const CENTIPAWNS_PER_PAWN: f32 = 100.0;
+
+impl Score {
+    pub const DRAW: Score = Score(0);
+    pub const MATE: Score = Score(32000);
+    // Bounds every score, mates included
+    pub const INFINITE: Score = Score(32001);
+
+    // Mates can't be further away than the deepest ply searched
+    const MATE_BOUND: i32 = Score::MATE.0 - MAX_PLY as i32;
+
+    pub const fn cp(centipawns: i32) -> Score {
+        Score(centipawns)
+    }
+
+    /// Converts the evaluation's pawn units.
+    pub fn from_pawns(pawns: f32) -> Score {
+        let cp = (pawns * CENTIPAWNS_PER_PAWN).round() as i32;
+
+        Score(cp.clamp(-Score::MATE_BOUND + 1, Score::MATE_BOUND - 1))
+    }
+
+    /// Mating the opponent `ply` plies from the root.
+    pub fn mate_in(ply: u8) -> Score {
+        Score(Score::MATE.0 - ply as i32)
+    }
+
+    /// Getting mated `ply` plies from the root.
+    pub fn mated_in(ply: u8) -> Score {
+        -Score::mate_in(ply)
+    }
+
+    pub fn is_mate(self) -> bool {
+        self.0.abs() >= Score::MATE_BOUND
+    }
+
+    /// Full moves to mate, negative when getting mated.
+    pub fn mate_moves(self) -> Option<i32> {
+        if !self.is_mate() {
+            return None;
+        }
+
+        let plies = Score::MATE.0 - self.0.abs();
+        let moves = (plies + 1) / 2;
+
+        Some(if self.0 > 0 { moves } else { -moves })
+    }
+
+    /// Mates are stored relative to the node rather than to the root, since
+    /// the same position can be reached at different plies.
+    pub fn to_tt(self, ply: u8) -> Score {
+        match self.0 {
+            s if s >= Score::MATE_BOUND => Score(s + ply as i32),
+            s if s <= -Score::MATE_BOUND => Score(s - ply as i32),
+            s => Score(s),
+        }
+    }
+
+    /// Undoes `to_tt` for a node `ply` plies from the root.
+    pub fn adjust_from_tt(self, ply: u8) -> Score {
+        match self.0 {
+            s if s >= Score::MATE_BOUND => Score(s - ply as i32),
+            s if s <= -Score::MATE_BOUND => Score(s + ply as i32),
+            s => Score(s),
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        Score(-self.0)
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score {
+        Score(self.0 + other.0)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score {
+        Score(self.0 - other.0)
+    }
+}
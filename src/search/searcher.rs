@@ -0,0 +1,139 @@
+use chess::{ChessMove, Color};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::search::pv::PvTable;
+use crate::search::killers::Killers;
+use crate::search::history::HistoryTable;
+use crate::search::extensions::ExtensionPolicy;
+use crate::search::pruning::PruningMargins;
+use crate::search::limits::{Limits, MAX_PLY};
+use crate::search::strength::{Strength, MAX_LEVEL};
+use crate::search::score::Score;
+use crate::search::time::TimeManager;
+
+// How often (in nodes) the clock is checked
+const CLOCK_CHECK_INTERVAL: u64 = 1024;
+
+// Root moves are only reported once the search has been running this long
+const CURRENT_MOVE_DELAY: Duration = Duration::from_secs(3);
+
+/// State of a single search, owned by the search thread.
+pub struct Searcher {
+    // Set by the GUI
+    pub stop: Arc<AtomicBool>,
+    // Set once one of the `go` limits is hit
+    pub is_limit_hit: bool,
+    pub limits: Limits,
+    pub time: TimeManager,
+    pub nodes: u64,
+    pub seldepth: u8,
+    pub pv: PvTable,
+    pub killers: Killers,
+    pub history_table: HistoryTable,
+    pub strength: Strength,
+    // Score of every root move searched by the current iteration
+    pub root_scores: Vec<(ChessMove, Score)>,
+    pub rng: ChaCha8Rng,
+    pub start: Instant,
+    // (hash, halfmove clock) of every position from the game start to the
+    // current node
+    pub history: Vec<(u64, u16)>,
+    pub root_side: Color,
+    // How much the root side dislikes a draw, see `draw_score`
+    pub contempt: Score,
+    // The move into the node about to be searched was a null move
+    pub after_null_move: bool,
+    // Move the node about to be searched must skip, see singular extensions
+    pub excluded_move: Option<ChessMove>,
+    pub extensions: ExtensionPolicy,
+    pub margins: PruningMargins,
+    // Plies extended along the current path, by ply
+    pub extended: [u8; MAX_PLY + 1],
+}
+
+impl Searcher {
+    pub fn new(limits: Limits, side: Color, move_overhead: Duration, seed: u64, stop: Arc<AtomicBool>) -> Searcher {
+        Searcher {
+            stop,
+            is_limit_hit: false,
+            time: TimeManager::new(&limits, side, move_overhead),
+            limits,
+            nodes: 0,
+            seldepth: 0,
+            pv: PvTable::new(),
+            killers: Killers::new(),
+            history_table: HistoryTable::new(),
+            strength: Strength::from_level(MAX_LEVEL),
+            root_scores: vec![],
+            // Ties are broken the same way on every run
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            start: Instant::now(),
+            history: vec![],
+            root_side: side,
+            contempt: Score::DRAW,
+            after_null_move: false,
+            excluded_move: None,
+            extensions: ExtensionPolicy::default(),
+            margins: PruningMargins::default(),
+            extended: [0; MAX_PLY + 1],
+        }
+    }
+
+    /// Enters the position reached with `hash`, resetting the halfmove clock
+    /// after captures and pawn moves.
+    pub fn push(&mut self, hash: u64, is_irreversible: bool) {
+        let halfmove = match self.history.last() {
+            Some(&(_, h)) if !is_irreversible => h.saturating_add(1),
+            _ => 0,
+        };
+
+        self.history.push((hash, halfmove));
+    }
+
+    pub fn pop(&mut self) {
+        self.history.pop();
+    }
+
+    /// Score of a draw for the side to move, keeping the root side away from
+    /// draws when contempt is positive.
+    pub fn draw_score(&self, side: Color) -> Score {
+        if side == self.root_side { -self.contempt } else { self.contempt }
+    }
+
+    /// Whether the GUI sent `stop`.
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Whether the search was asked to stop, either by the GUI or by its limits.
+    pub fn is_interruption(&self) -> bool {
+        self.is_limit_hit || self.is_stopped()
+    }
+
+    /// Flags the search for interruption once any of the `go` limits is hit.
+    pub fn check_limits(&mut self) {
+        if self.limits.nodes_exceeded(self.nodes) {
+            self.is_limit_hit = true;
+        }
+
+        if self.nodes.is_multiple_of(CLOCK_CHECK_INTERVAL) && self.time.hard_exceeded(self.start.elapsed()) {
+            self.is_limit_hit = true;
+        }
+    }
+
+    /// Whether `info currmove` lines are worth sending to the GUI.
+    pub fn is_reporting_current_move(&self) -> bool {
+        self.start.elapsed() >= CURRENT_MOVE_DELAY
+    }
+
+    /// Whether there is no time left to start another iteration.
+    pub fn is_soft_timeout(&self) -> bool {
+        self.time.soft_exceeded(self.start.elapsed())
+    }
+}
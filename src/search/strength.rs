@@ -0,0 +1,92 @@
+use chess::ChessMove;
+
+use rand::Rng;
+
+use crate::search::limits::Limits;
+use crate::search::score::Score;
+
+pub const MIN_LEVEL: u8 = 1;
+pub const MAX_LEVEL: u8 = 20;
+
+pub const MIN_ELO: u16 = 500;
+pub const MAX_ELO: u16 = 2500;
+
+// Centipawns a root move may lose against the best one per level below the top
+const RANDOMNESS_PER_LEVEL: i32 = 15;
+
+/// Playing strength, from `MIN_LEVEL` (beginner) to `MAX_LEVEL` (full strength).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strength {
+    pub max_depth: Option<u8>,
+    pub max_nodes: Option<u64>,
+    // How much worse than the best move a played move can be
+    pub randomness: Score,
+}
+
+impl Strength {
+    pub fn from_level(level: u8) -> Strength {
+        let level = level.clamp(MIN_LEVEL, MAX_LEVEL);
+
+        if level == MAX_LEVEL {
+            return Strength {
+                max_depth: None,
+                max_nodes: None,
+                randomness: Score::DRAW,
+            };
+        }
+
+        Strength {
+            max_depth: Some(1 + level / 3),
+            max_nodes: Some(1000 << (level / 2)),
+            randomness: Score::cp((MAX_LEVEL - level) as i32 * RANDOMNESS_PER_LEVEL),
+        }
+    }
+
+    /// Maps `UCI_Elo` linearly onto the levels.
+    pub fn from_elo(elo: u16) -> Strength {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        let span = (MAX_LEVEL - MIN_LEVEL) as u32;
+        let level = MIN_LEVEL as u32 + (elo - MIN_ELO) as u32 * span / (MAX_ELO - MIN_ELO) as u32;
+
+        Strength::from_level(level as u8)
+    }
+
+    pub fn is_randomized(&self) -> bool {
+        self.randomness > Score::DRAW
+    }
+
+    /// Tightens the `go` limits to the depth and node caps.
+    pub fn limit(&self, limits: &mut Limits) {
+        if let Some(max_depth) = self.max_depth {
+            limits.depth = Some(limits.depth.map_or(max_depth, |d| d.min(max_depth)));
+        }
+
+        if let Some(max_nodes) = self.max_nodes {
+            limits.nodes = Some(limits.nodes.map_or(max_nodes, |n| n.min(max_nodes)));
+        }
+    }
+
+    /// Picks one of the root moves that lose at most `randomness` against the
+    /// best one, the closer to the best the likelier.
+    pub fn pick<R: Rng>(&self, root_scores: &[(ChessMove, Score)], rng: &mut R) -> Option<ChessMove> {
+        let best = root_scores.iter().map(|(_, s)| *s).max()?;
+
+        let candidates: Vec<(ChessMove, i32)> = root_scores
+            .iter()
+            .filter(|(_, s)| best - *s <= self.randomness)
+            .map(|(m, s)| (*m, (self.randomness - (best - *s)).0 + 1))
+            .collect();
+
+        let total: i32 = candidates.iter().map(|(_, w)| w).sum();
+        let mut roll = rng.gen_range(0..total);
+
+        for (m, weight) in candidates.iter() {
+            if roll < *weight {
+                return Some(*m);
+            }
+            roll -= weight;
+        }
+
+        candidates.last().map(|(m, _)| *m)
+    }
+}
@@ -0,0 +1,168 @@
+use chess::Color;
+
+use std::time::Duration;
+
+use crate::search::limits::Limits;
+
+// Moves we plan for when the GUI doesn't send `movestogo`
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+const MAX_MOVES_TO_GO: u32 = 50;
+
+// The hard budget may stretch the soft one this many times...
+const HARD_SOFT_RATIO: u32 = 4;
+// ...but never past this share (in percent) of the remaining clock
+const HARD_CLOCK_PERCENT: u32 = 75;
+
+/// Per-move thinking budget.
+///
+/// The search shouldn't start new work once `soft` has elapsed and must
+/// stop as soon as `hard` has elapsed. `None` means there is no bound.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeManager {
+    pub soft: Option<Duration>,
+    pub hard: Option<Duration>,
+}
+
+impl TimeManager {
+    /// Computes the budget for `side` from the `go` limits, keeping
+    /// `overhead` aside for GUI and communication latency.
+    pub fn new(limits: &Limits, side: Color, overhead: Duration) -> TimeManager {
+        if limits.infinite {
+            return TimeManager::default();
+        }
+
+        if let Some(movetime) = limits.movetime {
+            let budget = movetime.saturating_sub(overhead);
+
+            return TimeManager {
+                soft: Some(budget),
+                hard: Some(budget),
+            };
+        }
+
+        let (time, inc) = match side {
+            Color::White => (limits.wtime, limits.winc),
+            Color::Black => (limits.btime, limits.binc),
+        };
+
+        let time = match time {
+            Some(t) => t,
+            None => return TimeManager::default(),
+        };
+
+        let inc = inc.unwrap_or_default();
+        let left = time.saturating_sub(overhead);
+        let moves_to_go = limits.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).clamp(1, MAX_MOVES_TO_GO);
+
+        let base = left / moves_to_go + inc * 3 / 4;
+        let hard = (base * HARD_SOFT_RATIO).min(left * HARD_CLOCK_PERCENT / 100);
+        let soft = base.min(hard);
+
+        TimeManager {
+            soft: Some(soft),
+            hard: Some(hard),
+        }
+    }
+
+    pub fn soft_exceeded(&self, elapsed: Duration) -> bool {
+        self.soft.is_some_and(|soft| elapsed >= soft)
+    }
+
+    pub fn hard_exceeded(&self, elapsed: Duration) -> bool {
+        self.hard.is_some_and(|hard| elapsed >= hard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::Color;
+
+    use std::time::Duration;
+
+    use super::TimeManager;
+    use crate::search::limits::Limits;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn budget(limits: Limits, side: Color, overhead: u64) -> (Option<Duration>, Option<Duration>) {
+        let tm = TimeManager::new(&limits, side, ms(overhead));
+
+        (tm.soft, tm.hard)
+    }
+
+    #[test]
+    fn movetime_keeps_the_overhead_aside() {
+        let limits = Limits { movetime: Some(ms(1000)), ..Limits::default() };
+
+        assert_eq!(budget(limits, Color::White, 50), (Some(ms(950)), Some(ms(950))));
+    }
+
+    #[test]
+    fn moves_to_go_defaults_to_30() {
+        let limits = Limits { wtime: Some(ms(30_000)), ..Limits::default() };
+
+        assert_eq!(budget(limits, Color::White, 0), (Some(ms(1000)), Some(ms(4000))));
+    }
+
+    #[test]
+    fn moves_to_go_is_clamped() {
+        let many = Limits { wtime: Some(ms(50_000)), movestogo: Some(100), ..Limits::default() };
+        let none = Limits { wtime: Some(ms(10_000)), movestogo: Some(0), ..Limits::default() };
+        let one = Limits { movestogo: Some(1), ..none.clone() };
+
+        assert_eq!(budget(many, Color::White, 0), (Some(ms(1000)), Some(ms(4000))));
+        assert_eq!(budget(none, Color::White, 0), budget(one, Color::White, 0));
+    }
+
+    #[test]
+    fn three_quarters_of_the_increment_are_spent() {
+        let limits = Limits {
+            wtime: Some(ms(1000)),
+            btime: Some(ms(30_000)),
+            winc: Some(ms(10_000)),
+            binc: Some(ms(2000)),
+            ..Limits::default()
+        };
+
+        assert_eq!(budget(limits, Color::Black, 0), (Some(ms(2500)), Some(ms(10_000))));
+    }
+
+    #[test]
+    fn hard_budget_keeps_a_quarter_of_the_clock() {
+        let limits = Limits { wtime: Some(ms(10_000)), movestogo: Some(1), ..Limits::default() };
+
+        assert_eq!(budget(limits, Color::White, 0), (Some(ms(7500)), Some(ms(7500))));
+    }
+
+    #[test]
+    fn overhead_over_the_clock_leaves_nothing() {
+        let clock = Limits { wtime: Some(ms(100)), winc: Some(ms(1000)), ..Limits::default() };
+        let movetime = Limits { movetime: Some(ms(100)), ..Limits::default() };
+
+        assert_eq!(budget(clock, Color::White, 200), (Some(ms(0)), Some(ms(0))));
+        assert_eq!(budget(movetime, Color::White, 200), (Some(ms(0)), Some(ms(0))));
+    }
+
+    #[test]
+    fn no_budget_without_a_clock() {
+        let infinite = Limits { infinite: true, wtime: Some(ms(1000)), ..Limits::default() };
+        let other_side = Limits { wtime: Some(ms(1000)), ..Limits::default() };
+
+        assert_eq!(budget(infinite, Color::White, 0), (None, None));
+        assert_eq!(budget(other_side, Color::Black, 0), (None, None));
+        assert_eq!(budget(Limits::default(), Color::White, 0), (None, None));
+    }
+
+    #[test]
+    fn budgets_are_exceeded_once_reached() {
+        let tm = TimeManager { soft: Some(ms(100)), hard: Some(ms(400)) };
+
+        assert!(!tm.soft_exceeded(ms(99)));
+        assert!(tm.soft_exceeded(ms(100)));
+        assert!(!tm.hard_exceeded(ms(399)));
+        assert!(tm.hard_exceeded(ms(400)));
+        assert!(!TimeManager::default().hard_exceeded(ms(u64::MAX)));
+    }
+}
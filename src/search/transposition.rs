@@ -1,11 +1,16 @@
 use chess::{
     Board,
+    ChessMove,
     Piece,
     BitBoard,
     Square,
     Color,
+    File,
 };
 use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+use crate::search::score::Score;
 
 pub const WHITE_PAWN: usize = 1;
 pub const WHITE_BISHOP: usize = 2;
@@ -39,70 +44,165 @@ pub const PIECES: [usize; 12] = [
 pub struct TT {
     pub table: [[u64; 13]; 64],
     pub black_to_move: u64,
+    pub castling: [u64; 16],
+    pub en_passant: [u64; 8],
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum Bound {
     Lower,
     Exact,
     Upper,
 }
 
+#[derive(Copy, Clone, Debug)]
 pub struct TableEntry {
     pub depth: u8,
-    pub score: f32,
+    // Mates relative to the node, see `Score::to_tt`
+    pub score: Score,
     pub age: u8,
     pub bound: Bound,
+    pub best_move: Option<ChessMove>,
 }
 
 impl TableEntry {
-    fn new(depth: u8, score: f32, age: u8, bound: Bound) -> TableEntry {
+    pub fn new(depth: u8, score: Score, age: u8, bound: Bound, best_move: Option<ChessMove>) -> TableEntry {
         TableEntry {
-            depth: depth,
-            score: score,
-            age: age,
-            bound: bound,
+            depth,
+            score,
+            age,
+            bound,
+            best_move,
         }
     }
 }
 
-impl TT {
-    pub fn new() -> TT {
-        TT {
-            table: [[0u64; 13]; 64],
-            black_to_move: 0u64,
-        }
+pub const DEFAULT_SEED: u64 = 0;
+
+pub const DEFAULT_HASH_MB: usize = 16;
+pub const MAX_HASH_MB: usize = 4096;
+
+// Entries sharing an index, only one of which gets replaced on a collision
+const BUCKET_SIZE: usize = 4;
+// Slots sampled to estimate how full the table is
+const HASHFULL_SAMPLE: usize = 1000;
+
+type Slot = Option<(u64, TableEntry)>;
+
+/// Fixed-size transposition table, split into buckets of `BUCKET_SIZE` entries.
+pub struct HashTable {
+    slots: Vec<Slot>,
+    age: u8,
+}
+
+impl HashTable {
+    pub fn new(mb: usize) -> HashTable {
+        let mut hash_table = HashTable {
+            slots: vec![],
+            age: 0,
+        };
+        hash_table.resize(mb);
+        hash_table
     }
 
-    pub fn from_board(board: &Board) -> TT {
-        // Fill the 12x64 table
-        let mut table: [[u64; 13]; 64] = [[0u64; 13]; 64];
+    /// Reallocates the table to `mb` megabytes, dropping every entry.
+    pub fn resize(&mut self, mb: usize) {
+        let buckets = (mb.clamp(1, MAX_HASH_MB) << 20) / (std::mem::size_of::<Slot>() * BUCKET_SIZE);
 
-        for sq in BitBoard::new(std::u64::MAX) {
-            for piece in PIECES.iter() {
-                table[sq.to_index()][*piece] = rand::thread_rng().gen::<u64>();
-            }
-        }
-        
-        TT {
-            table: table,
-            black_to_move: rand::thread_rng().gen::<u64>(),
-        }
+        self.slots = vec![None; buckets.max(1) * BUCKET_SIZE];
+        self.age = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+        self.age = 0;
+    }
+
+    /// Age of entries stored from now on, bumped once per search.
+    pub fn age(&self) -> u8 {
+        self.age
     }
 
-    pub fn update(&mut self, board: &Board) {
+    pub fn new_search(&mut self) {
+        self.age = self.age.wrapping_add(1);
+    }
+
+    pub fn get(&self, hash: u64) -> Option<&TableEntry> {
+        self.bucket(hash)
+            .iter()
+            .flatten()
+            .find(|(key, _)| *key == hash)
+            .map(|(_, entry)| entry)
+    }
+
+    /// Stores `entry` over the same position, an empty slot or, failing
+    /// those, the oldest and then shallowest entry of the bucket.
+    pub fn insert(&mut self, hash: u64, entry: TableEntry) {
+        let age = self.age;
+        let bucket = self.bucket_mut(hash);
+
+        let index = bucket
+            .iter()
+            .position(|slot| slot.is_none_or(|(key, _)| key == hash))
+            .unwrap_or_else(|| {
+                (0..BUCKET_SIZE)
+                    .min_by_key(|&i| {
+                        let (_, e) = bucket[i].unwrap();
+                        (e.age == age, e.depth)
+                    })
+                    .unwrap()
+            });
+
+        bucket[index] = Some((hash, entry));
+    }
+
+    /// Permille of the table used by the current search.
+    pub fn hashfull(&self) -> u16 {
+        let sample = &self.slots[..HASHFULL_SAMPLE.min(self.slots.len())];
+        let used = sample.iter().flatten().filter(|(_, e)| e.age == self.age).count();
+
+        (used * 1000 / sample.len()) as u16
+    }
+
+    fn bucket(&self, hash: u64) -> &[Slot] {
+        let start = self.bucket_start(hash);
+        &self.slots[start..start + BUCKET_SIZE]
+    }
+
+    fn bucket_mut(&mut self, hash: u64) -> &mut [Slot] {
+        let start = self.bucket_start(hash);
+        &mut self.slots[start..start + BUCKET_SIZE]
+    }
+
+    fn bucket_start(&self, hash: u64) -> usize {
+        (hash % (self.slots.len() / BUCKET_SIZE) as u64) as usize * BUCKET_SIZE
+    }
+}
+
+impl TT {
+    /// Draws every key from an RNG seeded with `seed`, so the same seed
+    /// always hashes positions the same way.
+    pub fn from_seed(seed: u64) -> TT {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
         // Fill the 12x64 table
         let mut table: [[u64; 13]; 64] = [[0u64; 13]; 64];
 
-        for sq in BitBoard::new(std::u64::MAX) {
+        for sq in BitBoard::new(u64::MAX) {
             for piece in PIECES.iter() {
-                table[sq.to_index()][*piece] = rand::thread_rng().gen::<u64>();
+                table[sq.to_index()][*piece] = rng.gen::<u64>();
             }
         }
 
-        self.table = table;
-        self.black_to_move = rand::thread_rng().gen::<u64>();
+        TT {
+            table,
+            black_to_move: rng.gen::<u64>(),
+            castling: rng.gen(),
+            en_passant: rng.gen(),
+        }
     }
 
+    /// Hashes `board` from scratch.
     pub fn hash(self, board: &Board) -> u64 {
         let mut hash = 0u64;
 
@@ -110,24 +210,170 @@ impl TT {
             hash ^= self.black_to_move;
         }
 
-        for sq in BitBoard::new(std::u64::MAX) {
-            if let Some(piece) = board.piece_on(sq) {
-                hash ^= self.table[sq.to_index()][piece_index(piece, sq, board)];
+        for sq in *board.combined() {
+            if let (Some(piece), Some(color)) = (board.piece_on(sq), board.color_on(sq)) {
+                hash ^= self.table[sq.to_index()][piece_index(piece, color)];
             }
         }
 
+        hash ^ self.castling[castling_index(board)] ^ self.en_passant_key(board)
+    }
+
+    /// Hash of `after`, reached by playing `m` on `board` whose hash is `hash`.
+    pub fn update_hash(self, hash: u64, board: &Board, m: ChessMove, after: &Board) -> u64 {
+        let us = board.side_to_move();
+        let (source, dest) = (m.get_source(), m.get_dest());
+        let moved = board.piece_on(source).unwrap();
+
+        let mut hash = hash ^ self.black_to_move;
+
+        // Moving piece, possibly promoted on arrival
+        hash ^= self.table[source.to_index()][piece_index(moved, us)];
+        hash ^= self.table[dest.to_index()][piece_index(m.get_promotion().unwrap_or(moved), us)];
+
+        if let Some(captured) = board.piece_on(dest) {
+            hash ^= self.table[dest.to_index()][piece_index(captured, !us)];
+        } else if moved == Piece::Pawn && source.get_file() != dest.get_file() {
+            // En passant, the captured pawn is beside the source square
+            let captured = Square::make_square(source.get_rank(), dest.get_file());
+            hash ^= self.table[captured.to_index()][piece_index(Piece::Pawn, !us)];
+        }
+
+        // Castling also moves the rook
+        if moved == Piece::King && source.get_file().to_index().abs_diff(dest.get_file().to_index()) == 2 {
+            let rank = source.get_rank();
+            let (from, to) = if dest.get_file() > source.get_file() { (File::H, File::F) } else { (File::A, File::D) };
+
+            hash ^= self.table[Square::make_square(rank, from).to_index()][piece_index(Piece::Rook, us)];
+            hash ^= self.table[Square::make_square(rank, to).to_index()][piece_index(Piece::Rook, us)];
+        }
+
+        hash ^= self.castling[castling_index(board)] ^ self.castling[castling_index(after)];
+        hash ^= self.en_passant_key(board) ^ self.en_passant_key(after);
+
         hash
     }
+
+    /// Hash of `after`, reached by passing the turn on `board`.
+    pub fn null_move_hash(self, hash: u64, board: &Board, after: &Board) -> u64 {
+        hash ^ self.black_to_move ^ self.en_passant_key(board) ^ self.en_passant_key(after)
+    }
+
+    fn en_passant_key(&self, board: &Board) -> u64 {
+        board.en_passant().map_or(0, |sq| self.en_passant[sq.get_file().to_index()])
+    }
 }
 
-pub fn piece_index(piece: Piece, square: Square, board: &Board) -> usize {
-    if let Some(piece) = board.piece_on(square) {
-        if let Some(color) = board.color_on(square) {
-            return piece.to_index() + 1 + if board.color_on(square).unwrap() == Color::White { 0 } else { 6 };
-        } else {
-            return 0;
+pub fn piece_index(piece: Piece, color: Color) -> usize {
+    piece.to_index() + 1 + if color == Color::White { 0 } else { 6 }
+}
+
+/// Both sides' castling rights packed into 4 bits.
+fn castling_index(board: &Board) -> usize {
+    board.castle_rights(Color::White).to_index() | board.castle_rights(Color::Black).to_index() << 2
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess::{Board, ChessMove, MoveGen, Piece};
+    use rand::prelude::*;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::TT;
+
+    // Starting points rich in castling, en passant and promotions
+    const POSITIONS: &[&str] = &[
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "r3k2r/1P4P1/8/2pP4/8/8/1p4p1/R3K2R w KQkq c6 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ];
+
+    const GAMES_PER_POSITION: usize = 50;
+    const MAX_GAME_PLIES: usize = 120;
+
+    /// Plays `m` and checks the incremental hash against a full rehash.
+    fn play(keys: TT, board: &Board, hash: u64, m: ChessMove) -> (Board, u64) {
+        let after = board.make_move_new(m);
+        let updated = keys.update_hash(hash, board, m, &after);
+
+        assert_eq!(updated, keys.hash(&after), "{} on {}", m, board);
+        (after, updated)
+    }
+
+    #[test]
+    fn incremental_hash_matches_full_hash_over_random_games() {
+        let keys = TT::from_seed(0);
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let (mut castles, mut en_passants, mut promotions, mut null_moves) = (0, 0, 0, 0);
+
+        for fen in POSITIONS {
+            for _ in 0..GAMES_PER_POSITION {
+                let mut board = Board::from_str(fen).unwrap();
+                let mut hash = keys.hash(&board);
+
+                for _ in 0..MAX_GAME_PLIES {
+                    if let Some(after) = board.null_move() {
+                        assert_eq!(keys.null_move_hash(hash, &board, &after), keys.hash(&after), "null move on {}", board);
+                        null_moves += 1;
+                    }
+
+                    let moves: Vec<ChessMove> = MoveGen::new_legal(&board).collect();
+                    let Some(&m) = moves.choose(&mut rng) else {
+                        break;
+                    };
+
+                    let moved = board.piece_on(m.get_source());
+                    let is_diagonal = m.get_source().get_file() != m.get_dest().get_file();
+
+                    if moved == Some(Piece::King) && m.get_source().get_file().to_index().abs_diff(m.get_dest().get_file().to_index()) == 2 {
+                        castles += 1;
+                    }
+                    if moved == Some(Piece::Pawn) && is_diagonal && board.piece_on(m.get_dest()).is_none() {
+                        en_passants += 1;
+                    }
+                    if m.get_promotion().is_some() {
+                        promotions += 1;
+                    }
+
+                    (board, hash) = play(keys, &board, hash, m);
+                }
+            }
+        }
+
+        // Random games must actually have gone through the tricky cases
+        assert!(castles > 0 && en_passants > 0 && promotions > 0 && null_moves > 0);
+    }
+
+    #[test]
+    fn incremental_hash_matches_full_hash_on_special_moves() {
+        let keys = TT::from_seed(0);
+        let cases = [
+            // Castling on both sides, for both colours
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1g1"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1c1"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8g8"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8c8"),
+            // Rook moves and captures that lose castling rights
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "a1a8"),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "h1h2"),
+            // Double push giving an en passant square, and the capture
+            ("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1", "e2e4"),
+            ("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1", "d4e3"),
+            // Promotions, quiet and capturing
+            ("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"),
+            ("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8n"),
+        ];
+
+        for (fen, m) in cases {
+            let board = Board::from_str(fen).unwrap();
+            let m = ChessMove::from_str(m).unwrap();
+
+            assert!(board.legal(m), "{} on {}", m, fen);
+            play(keys, &board, keys.hash(&board), m);
         }
-    } else {
-        return 0;
     }
-}
\ No newline at end of file
+}
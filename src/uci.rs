@@ -1,24 +1,88 @@
+use chess::{Board, ChessMove, Piece};
+
+use std::str::FromStr;
+
 use std::io;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+pub const DEFAULT_MOVE_OVERHEAD: u64 = 10;
+pub const MAX_MOVE_OVERHEAD: u64 = 5000;
+// Centipawns
+pub const MAX_CONTEMPT: i32 = 100;
+
+use crate::search::score::Score;
+use crate::search::limits::Limits;
+use crate::search::bench::BENCH_DEPTH;
+use crate::options::OPTIONS;
 
 pub enum Status {
-    Go(u8),
-    Position(String, Vec<String>),
+    Go(Limits),
+    SetOption(String, String),
+    NewGame,
+    Bench(u8),
+    Stop,
     Continue,
     Quit,
 }
 
+/// The game as set up by the last `position` command.
 pub struct Memory {
-    pub pos: String,
-    pub moves: Vec<String>,
+    // Position before any of the moves
+    pub start: Board,
+    pub moves: Vec<ChessMove>,
+    // Every position from `start` on with its halfmove clock, so never empty
+    pub history: Vec<(Board, u16)>,
 }
 
 impl Memory {
     pub fn new() -> Memory {
         Memory {
-            pos: "".to_string(),
+            start: Board::default(),
             moves: vec![],
+            history: vec![(Board::default(), 0)],
         }
     }
+
+    /// The position to search.
+    pub fn board(&self) -> Board {
+        self.history.last().unwrap().0
+    }
+
+    /// Replays `moves` from `fen`, leaving the game untouched if the FEN or
+    /// any of the moves is invalid.
+    pub fn set_position(&mut self, fen: &str, moves: &[String]) -> Result<(), String> {
+        let start = Board::from_str(fen).map_err(|e| e.to_string())?;
+
+        // The board itself doesn't keep the halfmove clock
+        let halfmove: u16 = fen.split_whitespace().nth(4).and_then(|h| h.parse().ok()).unwrap_or(0);
+        let mut history = vec![(start, halfmove)];
+        let mut played = vec![];
+
+        for m in moves {
+            let (board, halfmove) = *history.last().unwrap();
+
+            let chess_move = ChessMove::from_str(m)
+                .ok()
+                .filter(|cm| board.legal(*cm))
+                .ok_or(format!("Illegal move: {}", m))?;
+
+            let is_irreversible = board.piece_on(chess_move.get_source()) == Some(Piece::Pawn) || board.piece_on(chess_move.get_dest()).is_some();
+            let halfmove = if is_irreversible { 0 } else { halfmove.saturating_add(1) };
+
+            history.push((board.make_move_new(chess_move), halfmove));
+            played.push(chess_move);
+        }
+
+        self.start = start;
+        self.moves = played;
+        self.history = history;
+
+        Ok(())
+    }
 }
 
 fn identify() {
@@ -27,115 +91,260 @@ fn identify() {
 }
 
 fn options() {
-    println!("option name Level type spin default 1 min 1 max 20");
+    for option in OPTIONS {
+        println!("{}", option);
+    }
 }
 
 fn position(fen: String, moves: Vec<String>, mem: &mut Memory) {
-    mem.pos = fen;
-    mem.moves = moves;
+    if let Err(e) = mem.set_position(&fen, &moves) {
+        invalid_position(&e);
+    }
+}
+
+fn go(params: &[&str]) -> Limits {
+    let mut limits = Limits::default();
+    let mut params = params.iter();
+
+    while let Some(param) = params.next() {
+        match *param {
+            "infinite" => {
+                limits.infinite = true;
+            },
+            "depth" | "nodes" | "movetime" | "mate" | "wtime" | "btime" | "winc" | "binc" | "movestogo" => {
+                // Some GUIs send negative clocks when flagging
+                let value: u64 = match params.next().map(|v| v.parse::<i64>()) {
+                    Some(Ok(v)) => v.max(0) as u64,
+                    _ => {
+                        unknown_parameter(param);
+                        continue;
+                    }
+                };
+
+                match *param {
+                    "depth" => limits.depth = Some(value.min(u8::MAX as u64) as u8),
+                    "nodes" => limits.nodes = Some(value),
+                    "movetime" => limits.movetime = Some(Duration::from_millis(value)),
+                    "wtime" => limits.wtime = Some(Duration::from_millis(value)),
+                    "btime" => limits.btime = Some(Duration::from_millis(value)),
+                    "winc" => limits.winc = Some(Duration::from_millis(value)),
+                    "binc" => limits.binc = Some(Duration::from_millis(value)),
+                    "movestogo" => limits.movestogo = Some(value.min(u32::MAX as u64) as u32),
+                    _ => limits.mate = Some(value.min(u8::MAX as u64) as u8),
+                }
+            },
+            _ => {
+                unknown_parameter(param);
+            }
+        }
+    }
+
+    limits
+}
+
+pub fn best_move(move_str: String, ponder: Option<ChessMove>) {
+    match ponder {
+        Some(p) => println!("bestmove {} ponder {}", move_str, p),
+        None => println!("bestmove {}", move_str),
+    }
 }
 
-pub fn best_move(move_str: String) {
-    println!("bestmove {}", move_str);
+/// Reports a completed iteration.
+pub fn info(depth: u8, seldepth: u8, score: Score, nodes: u64, elapsed: Duration, hashfull: u16, pv: &[ChessMove]) {
+    let millis = elapsed.as_millis() as u64;
+    let nps = nodes * 1000 / millis.max(1);
+    let pv: Vec<String> = pv.iter().map(|m| m.to_string()).collect();
+
+    println!(
+        "info depth {} seldepth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+        depth, seldepth, score_to_uci(score), nodes, nps, millis, hashfull, pv.join(" "),
+    );
+}
+
+pub fn bench_result(nodes: u64, elapsed: Duration) {
+    let millis = elapsed.as_millis() as u64;
+
+    println!("Total time (ms): {}", millis);
+    println!("Nodes searched: {}", nodes);
+    println!("Nodes/second: {}", nodes * 1000 / millis.max(1));
+}
+
+/// Reports the root move being searched.
+pub fn current_move(m: ChessMove, number: usize) {
+    println!("info currmove {} currmovenumber {}", m, number);
+}
+
+/// Formats a search score as `cp <x>` or `mate <n>`.
+fn score_to_uci(score: Score) -> String {
+    match score.mate_moves() {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score.0),
+    }
 }
 
 fn unknown_command(command: &str) {
     println!("Unknown command: {}", command);
 }
 
+fn invalid_position(reason: &str) {
+    println!("Invalid position: {}", reason);
+}
+
 fn unknown_parameter(p: &str) {
     println!("Unknown parameter: {}", p);
 }
 
+pub fn unknown_option(name: &str) {
+    println!("Unknown option: {}", name);
+}
+
+pub fn invalid_option(name: &str, reason: &str) {
+    println!("Invalid value for option {}: {}", name, reason);
+}
+
+fn setoption(params: &[&str]) -> Status {
+    // setoption name <id> [value <x>], where both may contain spaces
+    let value_at = params.iter().position(|p| *p == "value").unwrap_or(params.len());
+
+    if params.first() != Some(&"name") || value_at < 2 {
+        unknown_parameter(params.first().unwrap_or(&""));
+        return Status::Continue;
+    }
+
+    let name = params[1..value_at].join(" ");
+    let value = params.get(value_at + 1..).map(|v| v.join(" ")).unwrap_or_default();
+
+    Status::SetOption(name, value)
+}
+
 pub fn parse(input: String, mem: &mut Memory) -> Status {
-    match input.as_str() {
-        "uci\n" => {
+    // Split command if it has arguments, which also drops "\r\n" and stray spaces
+    let split: Vec<&str> = input.split_whitespace().collect();
+
+    let Some((command, params)) = split.split_first() else {
+        return Status::Continue;
+    };
+
+    match *command {
+        "uci" => {
             identify();
             options();
             println!("uciok");
         },
-        "isready\n" => {
+        "isready" => {
             println!("readyok");
         },
-        "ucinewgame\n" => {
-            
+        "stop" => {
+            return Status::Stop;
         },
-        _ => {
-            // Split command if it has arguments
-            let split: Vec<&str> = input.split_whitespace().collect();
-            let command: &str;
-            if let Some(c) = split.get(0) {
-                command = c;
-            } else {
-                return Status::Continue;
-            }
-            
-            if command == "go" {
-                let depth: u8 = 6;
-                // if let Some(d_p) = split.get(2) {
-                //     if d_p == &"depth" {
-                //         if let Some(d) = split.get(3) {
-                //             depth = d.parse().expect("Failed");
-                //         }
-                //     }
-                // }
-                
-                return Status::Go(depth);
-            }
-            
-            let param: &str;
-            if let Some(p) = split.get(1) {
-                param = p;
-            } else {
-                return Status::Continue;
+        "quit" => {
+            return Status::Quit;
+        },
+        "ucinewgame" => {
+            return Status::NewGame;
+        },
+        "go" => {
+            return Status::Go(go(params));
+        },
+        "setoption" => {
+            return setoption(params);
+        },
+        // Not UCI, searches a fixed set of positions to compare node counts
+        "bench" => {
+            match params.first().map(|d| d.parse::<u8>()) {
+                None => return Status::Bench(BENCH_DEPTH),
+                Some(Ok(depth)) if depth > 0 => return Status::Bench(depth),
+                Some(_) => unknown_parameter(params[0]),
             }
-            
-            match command.as_ref() {
-                "position" => {
-                    if param == "fen" {
-                        let mut fen = "".to_string();
-                        let mut moves: Vec<String> = vec![];
-                        
-                        for (i, m) in split.iter().enumerate() {
-                            if i > 1 && i < 8 {
-                                fen = if fen == "" { m.to_string() } else { format!("{} {}", fen, m) };
-                            }
-                        }
-                        
-                        for (i, m) in split.iter().enumerate() {
-                            if i < 9 {
-                                continue;
-                            }
-                            moves.push(m.to_string());
-                        }
-                        
-                        position(fen.to_string(), moves.clone(), mem);
-                        return Status::Position(fen.to_string(), moves);
-                    } else if param == "startpos" {
-                        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
-                        let mut moves: Vec<String> = vec![];
-                        
-                        for (i, m) in split.iter().enumerate() {
-                            if i < 2 {
-                                continue;
-                            }
-                            moves.push(m.to_string());
-                        }
-                        
-                        position(fen.to_string(), moves.clone(), mem);
-                        return Status::Position(fen.to_string(), moves);
-                    } else {
-                        unknown_parameter(param);
-                    }
+        },
+        "position" => {
+            match position_fen(params) {
+                Ok(fen) => {
+                    let moves_at = params.iter().position(|p| *p == "moves").unwrap_or(params.len());
+                    let moves: Vec<String> = params[moves_at..].iter().skip(1).map(|m| m.to_string()).collect();
+
+                    position(fen, moves, mem);
                 },
-                _ => {
-                    unknown_command(command);
+                Err(e) => {
+                    invalid_position(&e);
                 }
             }
         },
+        _ => {
+            unknown_command(command);
+        }
     }
-    
-    return Status::Continue;
+
+    Status::Continue
+}
+
+/// The full six-field FEN of `position startpos|fen <fen> [moves ...]`.
+fn position_fen(params: &[&str]) -> Result<String, String> {
+    let moves_at = params.iter().position(|p| *p == "moves").unwrap_or(params.len());
+
+    match params[..moves_at].split_first() {
+        Some((&"startpos", [])) => Ok(STARTPOS.to_string()),
+        Some((&"startpos", extra)) => Err(format!("Unexpected tokens after startpos: {}", extra.join(" "))),
+        Some((&"fen", fields)) => fen(fields),
+        Some((other, _)) => Err(format!("Expected startpos or fen, got {}", other)),
+        None => Err("Missing startpos or fen".to_string()),
+    }
+}
+
+/// Validates FEN `fields`, filling in the halfmove clock and the move number
+/// when they are missing.
+fn fen(fields: &[&str]) -> Result<String, String> {
+    if !(4..=6).contains(&fields.len()) {
+        return Err(format!("FEN needs 4 to 6 fields, got {}", fields.len()));
+    }
+
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+
+    if ranks.len() != 8 {
+        return Err(format!("FEN placement needs 8 ranks, got {}", ranks.len()));
+    }
+
+    for rank in ranks.iter() {
+        let mut squares = 0;
+
+        for c in rank.chars() {
+            match c {
+                '1'..='8' => squares += c.to_digit(10).unwrap(),
+                'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => squares += 1,
+                _ => return Err(format!("Invalid piece '{}' in FEN rank {}", c, rank)),
+            }
+        }
+
+        if squares != 8 {
+            return Err(format!("FEN rank {} covers {} squares instead of 8", rank, squares));
+        }
+    }
+
+    if fields[1] != "w" && fields[1] != "b" {
+        return Err(format!("Side to move must be w or b, got {}", fields[1]));
+    }
+
+    if fields[2] != "-" && !fields[2].chars().all(|c| "KQkq".contains(c)) {
+        return Err(format!("Invalid castling rights {}", fields[2]));
+    }
+
+    if fields[3] != "-" && chess::Square::from_str(fields[3]).is_err() {
+        return Err(format!("Invalid en passant square {}", fields[3]));
+    }
+
+    let halfmove = fields.get(4).copied().unwrap_or("0");
+    let fullmove = fields.get(5).copied().unwrap_or("1");
+
+    if halfmove.parse::<u16>().is_err() || fullmove.parse::<u16>().is_err() {
+        return Err(format!("Invalid move counters {} {}", halfmove, fullmove));
+    }
+
+    let fen = format!("{} {} {} {} {} {}", fields[0], fields[1], fields[2], fields[3], halfmove, fullmove);
+
+    // Kings, checks and the like
+    Board::from_str(&fen).map_err(|e| e.to_string())?;
+
+    Ok(fen)
 }
 
 pub fn get_input() -> String {
@@ -146,4 +355,118 @@ pub fn get_input() -> String {
         .expect("Invalid input");
     
     buffer
-}
\ No newline at end of file
+}
+
+/// Reads stdin on its own thread so commands are received while searching.
+/// The channel is closed once stdin reaches EOF.
+pub fn input_channel() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || loop {
+        let input = get_input();
+
+        if input.is_empty() || sender.send(input).is_err() {
+            break;
+        }
+    });
+
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use chess::Board;
+
+    use super::{fen, parse, Memory, STARTPOS};
+
+    const AFTER_E4: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+    const KINGS: &str = "4k3/8/8/8/8/8/8/4K3";
+
+    /// Runs `input` on a game already at 1. e4 and returns it.
+    fn position(input: &str) -> Memory {
+        let mut mem = Memory::new();
+        parse("position startpos moves e2e4\n".to_string(), &mut mem);
+        parse(input.to_string(), &mut mem);
+
+        mem
+    }
+
+    #[test]
+    fn valid_positions() {
+        let cases = [
+            ("position startpos", STARTPOS, 0),
+            ("position startpos moves", STARTPOS, 0),
+            ("position startpos moves e2e4", AFTER_E4, 1),
+            ("position startpos moves e2e4 e7e5 g1f3", "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2", 3),
+            ("position fen 4k3/8/8/8/8/8/8/4K3 w - -", "4k3/8/8/8/8/8/8/4K3 w - - 0 1", 0),
+            ("position fen 4k3/8/8/8/8/8/8/4K3 w - - 7", "4k3/8/8/8/8/8/8/4K3 w - - 7 1", 0),
+            ("position fen 4k3/8/8/8/8/8/8/4K3 b - - 7 40", "4k3/8/8/8/8/8/8/4K3 b - - 7 40", 0),
+            ("position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1 moves e1e2", "4k3/8/8/8/8/8/4K3/8 b - - 1 1", 1),
+            ("position startpos moves e2e4\r\n", AFTER_E4, 1),
+            ("  position   startpos\tmoves  e2e4  \r\n", AFTER_E4, 1),
+        ];
+
+        for (input, expected, moves) in cases {
+            let mem = position(input);
+
+            assert_eq!(mem.board(), Board::from_str(expected).unwrap(), "{:?}", input);
+            assert_eq!(mem.moves.len(), moves, "{:?}", input);
+            assert_eq!(mem.history.len(), moves + 1, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn halfmove_clock_comes_from_the_fen() {
+        let mem = position(&format!("position fen {} w - - 7 1 moves e1e2", KINGS));
+
+        assert_eq!(mem.history.iter().map(|(_, h)| *h).collect::<Vec<u16>>(), vec![7, 8]);
+    }
+
+    #[test]
+    fn invalid_positions_keep_the_game() {
+        let cases = [
+            "position",
+            "position moves e2e4",
+            "position startpos e2e4",
+            "position startpos moves e2e5",
+            "position startpos moves e2e4 e2e4",
+            "position startpos moves e2e4 junk",
+            "position fen",
+            "position fen 4k3/8/8/8/8/8/8/4K3 w -",
+            "position fen 4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra",
+            "position fen 4k3/8/8/8/8/8/4K3 w - - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K3/8 w - - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K4 w - - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K2 w - - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4X3 w - - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K3 w KX - 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K3 w - e9 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K3 w - z3 0 1",
+            "position fen 4k3/8/8/8/8/8/8/4K3 w - - x 1",
+            "position fen 8/8/8/8/8/8/8/4K3 w - - 0 1",
+        ];
+
+        for input in cases {
+            let mem = position(input);
+
+            assert_eq!(mem.board(), Board::from_str(AFTER_E4).unwrap(), "{:?}", input);
+            assert_eq!(mem.moves.len(), 1, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn missing_counters_are_filled_in() {
+        let cases: [(&[&str], &str); 3] = [
+            (&[KINGS, "w", "-", "-"], "4k3/8/8/8/8/8/8/4K3 w - - 0 1"),
+            (&[KINGS, "b", "-", "-", "12"], "4k3/8/8/8/8/8/8/4K3 b - - 12 1"),
+            (&[KINGS, "w", "-", "-", "3", "9"], "4k3/8/8/8/8/8/8/4K3 w - - 3 9"),
+        ];
+
+        for (fields, expected) in cases {
+            assert_eq!(fen(fields).as_deref(), Ok(expected));
+        }
+    }
+}